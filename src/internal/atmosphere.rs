@@ -0,0 +1,112 @@
+use std::f32::consts::PI;
+
+use nalgebra_glm::Vec3;
+
+use super::entity::color::Color;
+use super::entity::light::Light;
+use super::picking::Ray;
+
+// Steps along the view ray, and along the ray towards the light from each of them
+const VIEW_SAMPLES: usize = 8;
+const LIGHT_SAMPLES: usize = 4;
+// Air thins out by e every this share of the shell's thickness
+const SCALE_HEIGHT: f32 = 0.25;
+
+/// A thin glowing shell around a planet, shaded with single scattering: sunlight is
+/// scattered once towards the eye and dimmed by the air it crosses on the way in and out.
+/// Rayleigh scattering turns the day side blue and the terminator orange, Mie scattering
+/// adds a haze around the sun.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Atmosphere {
+    pub radius: f32,        // Outer radius in world units, measured from the planet's center
+    pub planet_radius: f32, // Where the ground blocks the rays
+    pub rayleigh: Vec3,     // Rayleigh scattering per world unit at ground level, per color channel
+    pub mie: f32,           // Mie scattering per world unit at ground level, the same for every channel
+    pub mie_direction: f32, // Mie anisotropy in (-1, 1), positive scatters forwards around the sun
+    pub color: Color,       // Tint applied to the scattered light
+    pub intensity: f32,
+}
+
+impl Atmosphere {
+    /// Light scattered towards `eye` along the view ray through `position`, a point on the
+    /// outer shell of the atmosphere around `center`, as linear light.
+    pub fn scatter(&self, center: &Vec3, eye: &Vec3, position: &Vec3, lights: &[Light]) -> Vec3 {
+        let direction = (position - eye).normalize();
+        let view_ray = Ray { origin: *eye, direction };
+
+        // The part of the view ray inside the shell, cut short where it hits the ground
+        let Some((near, far)) = view_ray.sphere_crossings(center, self.radius) else {
+            return Vec3::zeros();
+        };
+        let near = near.max(0.0);
+        let far = match view_ray.sphere_crossings(center, self.planet_radius) {
+            Some((ground, _)) if ground > 0.0 => far.min(ground),
+            _ => far,
+        };
+        if far <= near {
+            return Vec3::zeros();
+        }
+
+        let scale_height = (self.radius - self.planet_radius) * SCALE_HEIGHT;
+        let extinction = self.rayleigh.add_scalar(self.mie);
+        let step = (far - near) / VIEW_SAMPLES as f32;
+
+        let mut view_depth = 0.0;
+        let mut rayleigh_sum = Vec3::zeros();
+        let mut mie_sum = Vec3::zeros();
+        for i in 0..VIEW_SAMPLES {
+            let sample = eye + direction * (near + (i as f32 + 0.5) * step);
+            let density = self.density(&sample, center, scale_height) * step;
+            view_depth += density;
+
+            for light in lights {
+                let light_dir = light.direction_from(&sample);
+                let light_ray = Ray { origin: sample, direction: light_dir };
+                // Samples on the night side get no sunlight at all
+                if matches!(light_ray.sphere_crossings(center, self.planet_radius), Some((ground, _)) if ground > 0.0) {
+                    continue;
+                }
+                let light_depth = self.optical_depth(&light_ray, center, scale_height);
+
+                // Sunlight dimmed on its way in to the sample and on its way out to the eye
+                let depth = view_depth + light_depth;
+                let transmittance = (-extinction * depth).map(f32::exp);
+                let incoming = light.color.to_linear() * light.intensity;
+                let scattered = incoming.component_mul(&transmittance) * density;
+
+                let cos_angle = direction.dot(&light_dir);
+                rayleigh_sum += scattered * rayleigh_phase(cos_angle);
+                mie_sum += scattered * henyey_greenstein(cos_angle, self.mie_direction);
+            }
+        }
+
+        let light = (rayleigh_sum.component_mul(&self.rayleigh) + mie_sum * self.mie) * self.intensity;
+        light.component_mul(&self.color.to_linear())
+    }
+
+    fn density(&self, position: &Vec3, center: &Vec3, scale_height: f32) -> f32 {
+        let height = ((position - center).norm() - self.planet_radius).max(0.0);
+        (-height / scale_height).exp()
+    }
+
+    // Air crossed from the ray's origin out of the shell towards the light
+    fn optical_depth(&self, ray: &Ray, center: &Vec3, scale_height: f32) -> f32 {
+        let Some((_, exit)) = ray.sphere_crossings(center, self.radius) else {
+            return 0.0;
+        };
+        let step = exit.max(0.0) / LIGHT_SAMPLES as f32;
+        (0..LIGHT_SAMPLES)
+            .map(|i| self.density(&(ray.origin + ray.direction * ((i as f32 + 0.5) * step)), center, scale_height) * step)
+            .sum()
+    }
+}
+
+fn rayleigh_phase(cos_angle: f32) -> f32 {
+    3.0 / (16.0 * PI) * (1.0 + cos_angle * cos_angle)
+}
+
+fn henyey_greenstein(cos_angle: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    (1.0 - g2) / (4.0 * PI * (1.0 + g2 - 2.0 * g * cos_angle).powf(1.5))
+}
+
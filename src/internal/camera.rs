@@ -1,10 +1,62 @@
-use nalgebra_glm::Vec3;
-use std::f32::consts::PI;
+use nalgebra_glm::{mat3_to_quat, quat_angle_axis, quat_dot, quat_normalize, quat_rotate_vec3, Mat3, Quat, Vec3};
+use serde::Deserialize;
 
+// How long switching between camera modes takes, in real seconds
+const TRANSITION_TIME: f32 = 1.0;
+
+pub const DEFAULT_FOV: f32 = 45.0; // Vertical field of view in degrees
+
+// Focusing on a body glides the orbit camera over for this long, in real seconds
+const RETARGET_TIME: f32 = 1.5;
+const FOCUS_DISTANCE: f32 = 3.0;   // Orbit radius after focusing, in radii of the focused body
+const FOCUS_MIN_DISTANCE: f32 = 1.2; // Zooming in stops this many radii away from the focused body
+const FOCUS_MAX_DISTANCE: f32 = 8.0; // And zooming out may go at least this far
+
+// Chase cam spring, stiff enough to keep up with the ship and damped so it doesn't wobble
+const CHASE_STIFFNESS: f32 = 30.0;
+const CHASE_DAMPING: f32 = 11.0;
+const CHASE_HEIGHT: f32 = 0.3;   // Height above the ship, relative to the chase distance
+const CHASE_UP_RATE: f32 = 4.0;  // How fast the camera rolls to match the ship, per second
+const CHASE_MAX_STEP: f32 = 1.0 / 120.0; // The spring is integrated in sub-steps no longer than this
+
+/// Ways the camera can move, switched at runtime with `Camera::next_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    #[default]
+    Orbit,   // Circles around `center`
+    FreeFly, // Moves and turns freely in every direction
+    Chase,   // Trails behind the ship
+    #[serde(skip)]
+    Path,    // Played back from a `CameraPath`, never picked by `next_mode`
+}
+
+impl CameraMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Orbit => "orbit",
+            CameraMode::FreeFly => "free fly",
+            CameraMode::Chase => "chase",
+            CameraMode::Path => "path",
+        }
+    }
+}
+
+/// Where the camera looks from, what `create_view_matrix` is built with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub eye: Vec3,
+    pub center: Vec3,
+    pub up: Vec3,
+}
+
+#[derive(Clone)]
 pub struct Camera {
-    pub eye: Vec3,          // Camera position
+    pub eye: Vec3,          // Camera position, follows from `center`, `orientation` and the radius
     pub center: Vec3,       // Subject origin position
-    pub up: Vec3,           // The upwards direction
+    pub up: Vec3,           // The upwards direction, follows from `orientation`
+    pub fov: f32,           // Vertical field of view in degrees
+    pub orientation: Quat,  // Rotates the camera's local axes into the world, it looks down its local -Z
     pub has_changed: bool,  // Tracks if the camera state has changed
 
     pub is_bird_view: bool,
@@ -12,10 +64,37 @@ pub struct Camera {
     pub bird_view_center: Vec3,
     pub cache_eye_view: Vec3,
     pub cache_center_view: Vec3,
+    pub cache_orientation: Quat,
 
     pub min_radius: f32,    // Minimum allowed radius
     pub max_radius: f32,    // Maximum allowed radius
     pub current_radius: f32, // Current radius
+
+    pub focus: usize,       // Index into `Scene::models` the orbit camera follows, the ship by default
+    pub focus_radius: f32,  // Bounding radius of the focused body, 0 until something is picked
+    retarget_from: (Vec3, f32), // Center and radius when the focus changed
+    retarget_radius: f32,
+    retarget: f32,          // 0 right after picking a new focus, 1 once the camera got there
+
+    pub mode: CameraMode,
+
+    // Free-fly camera
+    pub fly_eye: Vec3,
+    pub fly_orientation: Quat,
+
+    // Chase camera, `current_radius` sets how far behind the ship it stays
+    pub chase_eye: Vec3,
+    pub chase_velocity: Vec3,
+    pub chase_center: Vec3,
+    pub chase_up: Vec3,
+
+    // Camera path playback, set every frame by `CameraPathPlayer`
+    pub path_view: View,
+    pub path_fov: f32,
+
+    transition_from: View,
+    transition_from_fov: f32,
+    transition: f32, // 0 right after switching modes, 1 once the new mode has fully taken over
 }
 
 impl Camera {
@@ -28,63 +107,125 @@ impl Camera {
         bird_view_center: Vec3
     ) -> Self {
         let current_radius = (eye - center).magnitude().clamp(min_radius, max_radius);
+        let orientation = look_rotation(center - eye, up);
+        let up = quat_rotate_vec3(&orientation, &Vec3::y());
 
         Camera {
             eye: eye,
             center,
             up,
+            fov: DEFAULT_FOV,
+            orientation,
             has_changed: true,
             is_bird_view: false,
             bird_view_eye : bird_view_eye,
             bird_view_center: bird_view_center,
             cache_center_view : center,
             cache_eye_view : eye,
+            cache_orientation: orientation,
             min_radius,
             max_radius,
             current_radius,
+            focus: 0,
+            focus_radius: 0.0,
+            retarget_from: (center, current_radius),
+            retarget_radius: current_radius,
+            retarget: 1.0,
+            mode: CameraMode::Orbit,
+            fly_eye: eye,
+            fly_orientation: orientation,
+            chase_eye: eye,
+            chase_velocity: Vec3::zeros(),
+            chase_center: center,
+            chase_up: up,
+            path_view: View { eye, center, up },
+            path_fov: DEFAULT_FOV,
+            transition_from: View { eye, center, up },
+            transition_from_fov: DEFAULT_FOV,
+            transition: 1.0,
         }
     }
 
+    /// Circles around `center`, turning around the camera's own up and right axes.
+    /// There is no pitch limit, keep going and the camera loops over the poles.
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
-        let radius_vector = self.eye - self.center;
+        let yaw = quat_angle_axis(-delta_yaw, &Vec3::y());
+        let pitch = quat_angle_axis(delta_pitch, &Vec3::x());
+        self.orientation = quat_normalize(&(self.orientation * yaw * pitch));
 
-        let current_yaw = radius_vector.z.atan2(radius_vector.x);
-        let radius_xz = (radius_vector.x * radius_vector.x + radius_vector.z * radius_vector.z).sqrt();
-        let current_pitch = (-radius_vector.y).atan2(radius_xz);
-
-        // Calculate new yaw (horizontal) and pitch (vertical)
-        let new_yaw = (current_yaw + delta_yaw) % (2.0 * PI);
-        let new_pitch = (current_pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+        self.place_eye();
+    }
 
-        // Update the `eye` position using the current radius
-        self.eye = self.center + Vec3::new(
-            self.current_radius * new_yaw.cos() * new_pitch.cos(),
-            -self.current_radius * new_pitch.sin(),
-            self.current_radius * new_yaw.sin() * new_pitch.cos(),
-        );
+    /// Tilts the view around the direction the camera is looking.
+    pub fn roll(&mut self, delta_roll: f32) {
+        let roll = quat_angle_axis(delta_roll, &Vec3::z());
+        self.orientation = quat_normalize(&(self.orientation * roll));
 
-        self.has_changed = true;
+        self.place_eye();
     }
 
     pub fn change_center(&mut self, new_center: Vec3) {
-        let direction = (self.eye - self.center).normalize(); // Current direction to the subject
         self.center = new_center;
 
         // Update `eye` to maintain the fixed radius and direction
-        self.eye = self.center + direction * self.current_radius;
-
-        self.has_changed = true;
+        self.place_eye();
     }
 
     pub fn zoom(&mut self, delta: f32) {
         // Adjust the radius within allowed bounds
+        let (min_radius, max_radius) = self.radius_limits();
         self.current_radius = (self.current_radius - delta)
-            .clamp(self.min_radius, self.max_radius);
+            .clamp(min_radius, max_radius);
 
         // Update `eye` to maintain the direction and new radius
-        let direction = (self.eye - self.center).normalize();
-        self.eye = self.center + direction * self.current_radius;
+        self.place_eye();
+    }
+
+    /// Allowed orbit radius, widened so big focused bodies can't swallow the camera.
+    pub fn radius_limits(&self) -> (f32, f32) {
+        (
+            self.min_radius.max(self.focus_radius * FOCUS_MIN_DISTANCE),
+            self.max_radius.max(self.focus_radius * FOCUS_MAX_DISTANCE),
+        )
+    }
+
+    /// Makes model `focus` the orbit camera's subject, gliding over to it on the next `follow` calls.
+    /// `radius` is the body's bounding radius, the camera settles a few radii away from it.
+    pub fn focus_on(&mut self, focus: usize, radius: f32) {
+        self.focus = focus;
+        self.focus_radius = radius;
+
+        let (min_radius, max_radius) = self.radius_limits();
+        self.retarget_from = (self.center, self.current_radius);
+        self.retarget_radius = (radius * FOCUS_DISTANCE).clamp(min_radius, max_radius);
+        self.retarget = 0.0;
+    }
 
+    pub fn is_retargeting(&self) -> bool {
+        self.retarget < 1.0
+    }
+
+    /// Keeps the orbit camera centered on the focused body at `position`, `delta_time` in real seconds.
+    pub fn follow(&mut self, position: Vec3, delta_time: f32) {
+        if !self.is_retargeting() {
+            self.change_center(position);
+            return;
+        }
+
+        self.retarget = (self.retarget + delta_time / RETARGET_TIME).min(1.0);
+        let t = self.retarget * self.retarget * (3.0 - 2.0 * self.retarget);
+
+        // Aim at where the body is now, so the glide ends on it even while it orbits
+        let (from_center, from_radius) = self.retarget_from;
+        self.center = from_center + (position - from_center) * t;
+        self.current_radius = from_radius + (self.retarget_radius - from_radius) * t;
+        self.place_eye();
+    }
+
+    // Puts `eye` and `up` where `orientation` and the radius say, seen from `center`
+    fn place_eye(&mut self) {
+        self.eye = self.center + quat_rotate_vec3(&self.orientation, &Vec3::new(0.0, 0.0, self.current_radius));
+        self.up = quat_rotate_vec3(&self.orientation, &Vec3::y());
         self.has_changed = true;
     }
 
@@ -101,16 +242,193 @@ impl Camera {
         if self.is_bird_view {
             self.eye = self.cache_eye_view;
             self.center = self.cache_center_view;
+            self.orientation = self.cache_orientation;
             self.is_bird_view = false;
         } else {
 
             self.cache_eye_view = self.eye;
             self.cache_center_view = self.center;
+            self.cache_orientation = self.orientation;
 
             self.eye = self.bird_view_eye;
             self.center = self.bird_view_center;
+            self.orientation = look_rotation(self.center - self.eye, Vec3::y());
             self.is_bird_view = true;
         }
+        self.up = quat_rotate_vec3(&self.orientation, &Vec3::y());
         self.has_changed = true;
     }
-}
\ No newline at end of file
+
+    /// Switches to `mode`, easing over from the current view instead of jumping.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        let view = self.view();
+        let fov = self.field_of_view();
+
+        match mode {
+            CameraMode::Orbit => {}
+            CameraMode::FreeFly => {
+                // Start flying from wherever the camera is, looking the same way
+                self.fly_eye = view.eye;
+                self.fly_orientation = view.orientation();
+            }
+            CameraMode::Chase => {
+                self.chase_eye = view.eye;
+                self.chase_velocity = Vec3::zeros();
+                self.chase_center = view.center;
+                self.chase_up = view.up;
+            }
+            CameraMode::Path => {
+                self.path_view = view;
+                self.path_fov = fov;
+            }
+        }
+
+        self.mode = mode;
+        self.transition_from = view;
+        self.transition_from_fov = fov;
+        self.transition = 0.0;
+        self.has_changed = true;
+    }
+
+    pub fn next_mode(&mut self) {
+        let next = match self.mode {
+            CameraMode::Orbit => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Chase,
+            CameraMode::Chase | CameraMode::Path => CameraMode::Orbit,
+        };
+        self.set_mode(next);
+    }
+
+    /// Ends any transition in progress, so the current mode takes over right away.
+    pub fn snap(&mut self) {
+        self.transition = 1.0;
+        self.has_changed = true;
+    }
+
+    /// Advances the transition between modes by `delta_time` real seconds.
+    pub fn update(&mut self, delta_time: f32) {
+        if self.transition < 1.0 {
+            self.transition = (self.transition + delta_time / TRANSITION_TIME).min(1.0);
+            self.has_changed = true;
+        }
+    }
+
+    /// Moves the free-fly camera along its own axes and turns it around them.
+    /// `movement` is (right, up, back) like the ship's thrust, `rotation` is (pitch, yaw, roll).
+    pub fn fly(&mut self, movement: Vec3, rotation: Vec3) {
+        let turn = quat_angle_axis(rotation.x, &Vec3::x())
+            * quat_angle_axis(rotation.y, &Vec3::y())
+            * quat_angle_axis(rotation.z, &Vec3::z());
+        self.fly_orientation = quat_normalize(&(self.fly_orientation * turn));
+
+        self.fly_eye += quat_rotate_vec3(&self.fly_orientation, &movement);
+
+        self.has_changed = true;
+    }
+
+    /// Pulls the chase camera towards its spot behind `target` with a damped spring.
+    /// `forward` and `up` are the ship's own directions, `velocity` keeps the spring from lagging at speed.
+    pub fn chase(&mut self, target: Vec3, velocity: Vec3, forward: Vec3, up: Vec3, delta_time: f32) {
+        let anchor = target - forward * self.current_radius + up * (self.current_radius * CHASE_HEIGHT);
+
+        let steps = (delta_time / CHASE_MAX_STEP).ceil().max(1.0);
+        let h = delta_time / steps;
+        for i in 0..steps as usize {
+            // The target keeps moving during the frame, so aim at where it is at each sub-step
+            let lead = velocity * (h * (i + 1) as f32 - delta_time);
+            let offset = anchor + lead - self.chase_eye;
+            let acceleration = offset * CHASE_STIFFNESS + (velocity - self.chase_velocity) * CHASE_DAMPING;
+            self.chase_velocity += acceleration * h;
+            self.chase_eye += self.chase_velocity * h;
+        }
+
+        self.chase_center = target;
+        self.chase_up = (self.chase_up + (up - self.chase_up) * (1.0 - (-CHASE_UP_RATE * delta_time).exp())).normalize();
+        self.has_changed = true;
+    }
+
+    /// Sets where a camera path wants the camera, used while in `CameraMode::Path`.
+    pub fn follow_path(&mut self, view: View, fov: f32) {
+        self.path_view = view;
+        self.path_fov = fov;
+        self.has_changed = true;
+    }
+
+    /// Where the current mode looks from, ignoring any transition.
+    pub fn mode_view(&self) -> View {
+        match self.mode {
+            CameraMode::Orbit => View { eye: self.eye, center: self.center, up: self.up },
+            CameraMode::FreeFly => View::from_orientation(self.fly_eye, &self.fly_orientation),
+            CameraMode::Chase => View { eye: self.chase_eye, center: self.chase_center, up: self.chase_up },
+            CameraMode::Path => self.path_view,
+        }
+    }
+
+    /// Field of view to render with, in degrees, eased along with the view while switching modes.
+    pub fn field_of_view(&self) -> f32 {
+        let target = if self.mode == CameraMode::Path { self.path_fov } else { self.fov };
+        let t = self.transition_progress();
+        self.transition_from_fov + (target - self.transition_from_fov) * t
+    }
+
+    // Eased transition progress, 0 at the previous mode and 1 at the current one
+    fn transition_progress(&self) -> f32 {
+        let t = self.transition.clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// What gets rendered: the current mode's view, blended with the previous one while switching.
+    pub fn view(&self) -> View {
+        let target = self.mode_view();
+        if self.transition >= 1.0 {
+            return target;
+        }
+
+        let t = self.transition_progress();
+        let from = self.transition_from;
+
+        // Blend orientations rather than the centers, they can be far apart between modes
+        let eye = from.eye + (target.eye - from.eye) * t;
+        let orientation = nlerp(&from.orientation(), &target.orientation(), t);
+
+        View::from_orientation(eye, &orientation)
+    }
+}
+
+impl View {
+    /// A view from `eye` looking down the local -Z of `orientation`.
+    pub fn from_orientation(eye: Vec3, orientation: &Quat) -> Self {
+        View {
+            eye,
+            center: eye + quat_rotate_vec3(orientation, &Vec3::new(0.0, 0.0, -1.0)),
+            up: quat_rotate_vec3(orientation, &Vec3::y()),
+        }
+    }
+
+    pub fn orientation(&self) -> Quat {
+        look_rotation(self.center - self.eye, self.up)
+    }
+}
+
+/// Orientation whose local -Z points along `forward` with +Y as close to `up` as possible.
+pub fn look_rotation(forward: Vec3, up: Vec3) -> Quat {
+    let back = -forward.normalize();
+    let mut right = up.cross(&back);
+    if right.norm_squared() < 1e-8 {
+        // Looking straight along `up`, any sideways axis will do
+        right = Vec3::x().cross(&back);
+        if right.norm_squared() < 1e-8 {
+            right = Vec3::z().cross(&back);
+        }
+    }
+    let right = right.normalize();
+    let up = back.cross(&right);
+
+    quat_normalize(&mat3_to_quat(&Mat3::from_columns(&[right, up, back])))
+}
+
+// Normalized lerp along the shorter way around, close enough to slerp for easing between views
+fn nlerp(from: &Quat, to: &Quat, t: f32) -> Quat {
+    let to = if quat_dot(from, to) < 0.0 { -to } else { *to };
+    quat_normalize(&(from * (1.0 - t) + to * t))
+}
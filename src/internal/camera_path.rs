@@ -0,0 +1,384 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+
+use super::camera::{Camera, CameraMode, View, DEFAULT_FOV};
+
+// Seconds between keyframes recorded from the live camera, edit the file to retime them
+const RECORD_SPACING: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    #[default]
+    CatmullRom, // Passes through every keyframe with automatic tangents
+    Bezier,     // Like Catmull-Rom, but keyframes can set their own handles to shape the curve
+}
+
+/// How time is spread over a segment between two keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// One shot of the path: where the camera is at `time` seconds into the playback.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    pub time: f32,
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+    #[serde(default = "default_fov")]
+    pub fov: f32, // Degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub easing: Option<Easing>, // Overrides the path's easing for the segment leaving this keyframe
+    // Bezier handles, offsets from `eye` / `target` towards the next keyframe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eye_handle: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_handle: Option<[f32; 3]>,
+}
+
+/// A scripted camera flight, stored as a TOML file of keyframes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraPath {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default)]
+    pub looping: bool,
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug)]
+pub enum CameraPathError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, source: toml::de::Error },
+    Serialize { source: toml::ser::Error },
+    InvalidValue { message: String },
+}
+
+impl fmt::Display for CameraPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraPathError::Io { path, source } => write!(f, "could not access camera path file '{}': {}", path, source),
+            CameraPathError::Parse { path, source } => write!(f, "could not parse camera path file '{}': {}", path, source),
+            CameraPathError::Serialize { source } => write!(f, "could not write camera path: {}", source),
+            CameraPathError::InvalidValue { message } => write!(f, "camera path is invalid: {}", message),
+        }
+    }
+}
+
+impl Error for CameraPathError {}
+
+impl CameraPath {
+    pub fn load(path: &Path) -> Result<CameraPath, CameraPathError> {
+        let source = fs::read_to_string(path).map_err(|source| CameraPathError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        let camera_path: CameraPath = toml::from_str(&source).map_err(|source| CameraPathError::Parse {
+            path: path.display().to_string(),
+            source,
+        })?;
+        camera_path.validate()?;
+        Ok(camera_path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CameraPathError> {
+        let source = toml::to_string(self).map_err(|source| CameraPathError::Serialize { source })?;
+        fs::write(path, source).map_err(|source| CameraPathError::Io {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), CameraPathError> {
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            if index > 0 && keyframe.time <= self.keyframes[index - 1].time {
+                return Err(CameraPathError::InvalidValue {
+                    message: format!("keyframe {} must come after the previous one in time", index),
+                });
+            }
+            if !(keyframe.fov > 0.0 && keyframe.fov < 180.0) {
+                return Err(CameraPathError::InvalidValue {
+                    message: format!("keyframe {} needs a fov between 0 and 180 degrees", index),
+                });
+            }
+            if to_vec3(keyframe.eye) == to_vec3(keyframe.target) || to_vec3(keyframe.up) == Vec3::zeros() {
+                return Err(CameraPathError::InvalidValue {
+                    message: format!("keyframe {} needs a target away from its eye and a non-zero up", index),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f32 {
+        match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Appends the camera's current view as a keyframe a few seconds after the last one.
+    pub fn record(&mut self, camera: &Camera) {
+        let view = camera.view();
+        let time = self.keyframes.last().map_or(0.0, |last| last.time + RECORD_SPACING);
+
+        self.keyframes.push(Keyframe {
+            time,
+            eye: view.eye.into(),
+            target: view.center.into(),
+            up: view.up.into(),
+            fov: camera.field_of_view(),
+            easing: None,
+            eye_handle: None,
+            target_handle: None,
+        });
+    }
+
+    /// The view and field of view `time` seconds into the path, `None` for an empty path.
+    pub fn sample(&self, time: f32) -> Option<(View, f32)> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        let time = if self.looping && self.duration() > 0.0 {
+            first.time + (time - first.time).rem_euclid(self.duration())
+        } else {
+            time.clamp(first.time, last.time)
+        };
+
+        // Segment [i, i + 1] holding `time`, the last keyframe ends the final segment
+        let i = self.keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap_or(0);
+        let next = (i + 1).min(self.keyframes.len() - 1);
+        let (k1, k2) = (&self.keyframes[i], &self.keyframes[next]);
+        if i == next {
+            return Some((keyframe_view(to_vec3(k1.eye), to_vec3(k1.target), to_vec3(k1.up)), k1.fov));
+        }
+
+        // Neighbours for the tangents, the ends repeat themselves
+        let k0 = &self.keyframes[i.saturating_sub(1)];
+        let k3 = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)];
+
+        let easing = k1.easing.unwrap_or(self.easing);
+        let t = easing.apply((time - k1.time) / (k2.time - k1.time));
+
+        let eye = self.curve([k0.eye, k1.eye, k2.eye, k3.eye], [k1.eye_handle, k2.eye_handle], t);
+        let target = self.curve([k0.target, k1.target, k2.target, k3.target], [k1.target_handle, k2.target_handle], t);
+        let up = to_vec3(k1.up) + (to_vec3(k2.up) - to_vec3(k1.up)) * t;
+        let fov = k1.fov + (k2.fov - k1.fov) * t;
+
+        Some((keyframe_view(eye, target, up), fov))
+    }
+
+    // Point at `t` on the segment between points[1] and points[2]
+    fn curve(&self, points: [[f32; 3]; 4], handles: [Option<[f32; 3]>; 2], t: f32) -> Vec3 {
+        let [p0, p1, p2, p3] = points.map(to_vec3);
+
+        match self.interpolation {
+            Interpolation::CatmullRom => catmull_rom(p0, p1, p2, p3, t),
+            Interpolation::Bezier => {
+                // Missing handles fall back to the Catmull-Rom tangents, so both curves agree by default
+                let out_handle = handles[0].map_or((p2 - p0) / 6.0, to_vec3);
+                let in_handle = handles[1].map_or((p3 - p1) / 6.0, to_vec3);
+                bezier(p1, p1 + out_handle, p2 - in_handle, p2, t)
+            }
+        }
+    }
+}
+
+/// Plays a `CameraPath` back by steering the camera through `CameraMode::Path`.
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32, // Seconds since playback started
+    pub playing: bool,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        CameraPathPlayer { path, time: 0.0, playing: false }
+    }
+
+    /// Starts from the first keyframe, easing over from the current view unless `snap` is set.
+    pub fn play(&mut self, camera: &mut Camera, snap: bool) {
+        if self.path.keyframes.is_empty() {
+            return;
+        }
+        self.time = self.path.keyframes[0].time;
+        self.playing = true;
+
+        camera.set_mode(CameraMode::Path);
+        self.apply(camera);
+        if snap {
+            camera.snap();
+        }
+    }
+
+    /// Hands the camera back to the orbit mode.
+    pub fn stop(&mut self, camera: &mut Camera) {
+        self.playing = false;
+        if camera.mode == CameraMode::Path {
+            camera.set_mode(CameraMode::Orbit);
+        }
+    }
+
+    /// Moves playback along by `delta_time` seconds and stops at the end of a non-looping path.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: f32) {
+        if !self.playing {
+            return;
+        }
+        // Switching camera modes by hand ends the playback
+        if camera.mode != CameraMode::Path {
+            self.playing = false;
+            return;
+        }
+
+        self.time += delta_time;
+        self.apply(camera);
+
+        let end = self.path.keyframes.last().map_or(0.0, |last| last.time);
+        if !self.path.looping && self.time >= end {
+            self.stop(camera);
+        }
+    }
+
+    fn apply(&self, camera: &mut Camera) {
+        if let Some((view, fov)) = self.path.sample(self.time) {
+            camera.follow_path(view, fov);
+        }
+    }
+}
+
+fn keyframe_view(eye: Vec3, target: Vec3, up: Vec3) -> View {
+    View { eye, center: target, up: up.normalize() }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+fn bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+}
+
+fn to_vec3(values: [f32; 3]) -> Vec3 {
+    Vec3::new(values[0], values[1], values[2])
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_fov() -> f32 {
+    DEFAULT_FOV
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, eye: [f32; 3], fov: f32) -> Keyframe {
+        Keyframe {
+            time,
+            eye,
+            target: [0.0, 0.0, 0.0],
+            up: default_up(),
+            fov,
+            easing: None,
+            eye_handle: None,
+            target_handle: None,
+        }
+    }
+
+    fn path(interpolation: Interpolation, looping: bool) -> CameraPath {
+        CameraPath {
+            interpolation,
+            easing: Easing::Linear,
+            looping,
+            keyframes: vec![
+                keyframe(0.0, [10.0, 0.0, 0.0], 40.0),
+                keyframe(2.0, [0.0, 0.0, 10.0], 60.0),
+                keyframe(4.0, [-10.0, 0.0, 0.0], 40.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn sample_passes_through_every_keyframe() {
+        for interpolation in [Interpolation::CatmullRom, Interpolation::Bezier] {
+            let path = path(interpolation, false);
+            for keyframe in &path.keyframes {
+                let (view, fov) = path.sample(keyframe.time).unwrap();
+                assert!((view.eye - to_vec3(keyframe.eye)).norm() < 1e-4);
+                assert_eq!(fov, keyframe.fov);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_blends_between_keyframes() {
+        let (_, fov) = path(Interpolation::CatmullRom, false).sample(1.0).unwrap();
+        assert!((fov - 50.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sample_holds_the_ends_or_wraps_around() {
+        let (view, _) = path(Interpolation::CatmullRom, false).sample(10.0).unwrap();
+        assert!((view.eye - Vec3::new(-10.0, 0.0, 0.0)).norm() < 1e-4);
+
+        let (view, _) = path(Interpolation::CatmullRom, true).sample(6.0).unwrap();
+        assert!((view.eye - Vec3::new(0.0, 0.0, 10.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn empty_path_has_nothing_to_sample() {
+        assert!(CameraPath::default().sample(0.0).is_none());
+    }
+
+    #[test]
+    fn validate_rejects_bad_keyframes() {
+        assert!(path(Interpolation::CatmullRom, false).validate().is_ok());
+
+        let mut unordered = path(Interpolation::CatmullRom, false);
+        unordered.keyframes[2].time = 1.0;
+        assert!(unordered.validate().is_err());
+
+        let mut wide = path(Interpolation::CatmullRom, false);
+        wide.keyframes[0].fov = 180.0;
+        assert!(wide.validate().is_err());
+
+        let mut blind = path(Interpolation::CatmullRom, false);
+        blind.keyframes[1].eye = blind.keyframes[1].target;
+        assert!(blind.validate().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use super::entity::vertex::Vertex;
+
+/// Frustum planes in homogeneous clip space as (a, b, c, d): a point is inside when
+/// a*x + b*y + c*z + d*w >= 0. Near and far come first since they are the ones that
+/// actually matter for correctness, the rest only trim work for the rasterizer.
+const FRUSTUM_PLANES: [[f32; 4]; 6] = [
+    [0.0, 0.0, 1.0, 1.0],  // near:   z >= -w
+    [0.0, 0.0, -1.0, 1.0], // far:    z <= w
+    [1.0, 0.0, 0.0, 1.0],  // left:   x >= -w
+    [-1.0, 0.0, 0.0, 1.0], // right:  x <= w
+    [0.0, 1.0, 0.0, 1.0],  // bottom: y >= -w
+    [0.0, -1.0, 0.0, 1.0], // top:    y <= w
+];
+
+fn plane_distance(plane: &[f32; 4], vertex: &Vertex) -> f32 {
+    let p = vertex.clip_position;
+    plane[0] * p.x + plane[1] * p.y + plane[2] * p.z + plane[3] * p.w
+}
+
+/// Clips a triangle against the view frustum (Sutherland–Hodgman) and fans the
+/// resulting polygon back into triangles. Vertices must carry `clip_position`.
+pub fn clip_triangle(a: &Vertex, b: &Vertex, c: &Vertex) -> Vec<[Vertex; 3]> {
+    let vertices = [a, b, c];
+
+    // Trivial accept: every vertex inside every plane
+    if FRUSTUM_PLANES
+        .iter()
+        .all(|plane| vertices.iter().all(|v| plane_distance(plane, v) >= 0.0))
+    {
+        return vec![[a.clone(), b.clone(), c.clone()]];
+    }
+
+    // Trivial reject: every vertex outside the same plane
+    if FRUSTUM_PLANES
+        .iter()
+        .any(|plane| vertices.iter().all(|v| plane_distance(plane, v) < 0.0))
+    {
+        return Vec::new();
+    }
+
+    let mut polygon = vec![a.clone(), b.clone(), c.clone()];
+    for plane in &FRUSTUM_PLANES {
+        polygon = clip_polygon(&polygon, plane);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+fn clip_polygon(polygon: &[Vertex], plane: &[f32; 4]) -> Vec<Vertex> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = plane_distance(plane, current);
+        let next_distance = plane_distance(plane, next);
+
+        if current_distance >= 0.0 {
+            output.push(current.clone());
+        }
+
+        // The edge crosses the plane: emit the intersection point
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            output.push(current.lerp(next, t));
+        }
+    }
+
+    output
+}
+
+/// Clips a line segment against the view frustum, returning the visible part if any.
+pub fn clip_line(a: &Vertex, b: &Vertex) -> Option<(Vertex, Vertex)> {
+    let mut t_start: f32 = 0.0;
+    let mut t_end: f32 = 1.0;
+
+    for plane in &FRUSTUM_PLANES {
+        let a_distance = plane_distance(plane, a);
+        let b_distance = plane_distance(plane, b);
+
+        if a_distance < 0.0 && b_distance < 0.0 {
+            return None;
+        }
+
+        let t = a_distance / (a_distance - b_distance);
+        if a_distance < 0.0 {
+            t_start = t_start.max(t);
+        } else if b_distance < 0.0 {
+            t_end = t_end.min(t);
+        }
+    }
+
+    if t_start > t_end {
+        return None;
+    }
+
+    Some((a.lerp(b, t_start), a.lerp(b, t_end)))
+}
+
+/// Perspective divide and viewport transform for a vertex that already passed clipping.
+pub fn project_to_screen(vertex: &mut Vertex, viewport_matrix: &Mat4) {
+    let clip = vertex.clip_position;
+    let ndc_position = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen_position = viewport_matrix * ndc_position;
+
+    vertex.frustrum_position = ndc_position;
+    vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec2;
+
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        Vertex::new(Vec3::zeros(), Vec3::zeros(), Vec2::zeros(), Vec4::new(x, y, z, w))
+    }
+
+    fn inside_near(vertex: &Vertex) -> bool {
+        vertex.clip_position.z + vertex.clip_position.w >= -1e-5
+    }
+
+    #[test]
+    fn triangle_inside_is_kept_as_is() {
+        let triangles = clip_triangle(&vertex(-0.5, -0.5, 0.0, 1.0), &vertex(0.5, -0.5, 0.0, 1.0), &vertex(0.0, 0.5, 0.0, 1.0));
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0][1].clip_position, Vec4::new(0.5, -0.5, 0.0, 1.0));
+    }
+
+    #[test]
+    fn triangle_behind_the_camera_is_dropped() {
+        let triangles = clip_triangle(&vertex(-0.5, -0.5, -2.0, 1.0), &vertex(0.5, -0.5, -2.0, 1.0), &vertex(0.0, 0.5, -3.0, 1.0));
+        assert!(triangles.is_empty());
+    }
+
+    #[test]
+    fn triangle_straddling_the_near_plane_is_cut_at_it() {
+        // One corner behind the near plane leaves a quad, fanned into two triangles
+        let triangles = clip_triangle(&vertex(0.0, 0.5, -2.0, 1.0), &vertex(-0.5, -0.5, 0.0, 1.0), &vertex(0.5, -0.5, 0.0, 1.0));
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().flatten().all(inside_near));
+        assert!(triangles.iter().flatten().any(|vertex| (vertex.clip_position.z + vertex.clip_position.w).abs() < 1e-5));
+    }
+
+    #[test]
+    fn line_inside_is_kept_whole() {
+        let (a, b) = clip_line(&vertex(-0.5, 0.0, 0.0, 1.0), &vertex(0.5, 0.0, 0.0, 1.0)).unwrap();
+        assert_eq!(a.clip_position, Vec4::new(-0.5, 0.0, 0.0, 1.0));
+        assert_eq!(b.clip_position, Vec4::new(0.5, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn line_outside_is_dropped() {
+        assert!(clip_line(&vertex(0.0, 0.0, -2.0, 1.0), &vertex(0.5, 0.0, -3.0, 1.0)).is_none());
+        assert!(clip_line(&vertex(2.0, 0.0, 0.0, 1.0), &vertex(3.0, 0.5, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn line_straddling_the_near_plane_starts_on_it() {
+        let (a, b) = clip_line(&vertex(0.0, 0.0, -3.0, 1.0), &vertex(0.0, 0.0, 0.0, 1.0)).unwrap();
+        assert!((a.clip_position.z + a.clip_position.w).abs() < 1e-5);
+        assert_eq!(b.clip_position, Vec4::new(0.0, 0.0, 0.0, 1.0));
+    }
+}
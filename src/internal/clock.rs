@@ -0,0 +1,130 @@
+// Never run more than this many steps in one frame, so a slow frame can't snowball into slower ones
+const MAX_STEPS_PER_FRAME: u32 = 240;
+
+/// Turns real elapsed time into a whole number of fixed simulation steps.
+/// Leftover time is carried over, so the simulation runs at the same speed whatever the frame rate.
+pub struct SimulationClock {
+    pub step: f32,       // Length of one simulation step in seconds
+    pub time_scale: f32, // Simulated seconds per real second, negative runs time backwards
+    pub paused: bool,
+    time: f32,
+    accumulator: f32,
+    single_step: bool,
+}
+
+impl SimulationClock {
+    pub fn new(step: f32) -> Self {
+        SimulationClock {
+            step,
+            time_scale: 1.0,
+            paused: false,
+            time: 0.0,
+            accumulator: 0.0,
+            single_step: false,
+        }
+    }
+
+    /// Simulation time in seconds, what shaders see as `Uniforms::time`.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Signed length of the steps handed out by `advance`.
+    pub fn step_delta(&self) -> f32 {
+        if self.time_scale < 0.0 { -self.step } else { self.step }
+    }
+
+    /// Adds `real_delta` seconds of wall clock time and returns how many steps of
+    /// `step_delta` the simulation should take now.
+    pub fn advance(&mut self, real_delta: f32) -> u32 {
+        if self.paused {
+            if !self.single_step {
+                return 0;
+            }
+            self.single_step = false;
+            self.time += self.step_delta();
+            return 1;
+        }
+
+        self.accumulator += real_delta * self.time_scale.abs();
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < MAX_STEPS_PER_FRAME {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if steps == MAX_STEPS_PER_FRAME {
+            self.accumulator = 0.0;
+        }
+
+        self.time += self.step_delta() * steps as f32;
+        steps
+    }
+
+    /// Takes back simulated time a step handed out by `advance` could not use,
+    /// e.g. when rewinding runs into the start of the time loop.
+    pub fn refund(&mut self, seconds: f32) {
+        self.time -= seconds;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.accumulator = 0.0;
+    }
+
+    /// While paused, lets exactly one step through on the next `advance`.
+    pub fn request_step(&mut self) {
+        self.single_step = self.paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_hands_out_whole_steps_and_carries_the_rest() {
+        let mut clock = SimulationClock::new(0.1);
+        assert_eq!(clock.advance(0.25), 2);
+        assert_eq!(clock.advance(0.05), 1);
+        assert!((clock.time() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reverse_playback_runs_time_backwards() {
+        let mut clock = SimulationClock::new(0.25);
+        clock.time_scale = -2.0;
+        assert_eq!(clock.step_delta(), -0.25);
+        assert_eq!(clock.advance(0.5), 4);
+        assert_eq!(clock.time(), -1.0);
+    }
+
+    #[test]
+    fn refund_takes_back_unused_time() {
+        let mut clock = SimulationClock::new(0.1);
+        clock.time_scale = -1.0;
+        clock.advance(0.1);
+        // The step only got halfway before the loop start stopped it
+        clock.refund(-0.05);
+        assert!((clock.time() + 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn paused_clock_only_lets_single_steps_through() {
+        let mut clock = SimulationClock::new(0.1);
+        clock.toggle_pause();
+        assert_eq!(clock.advance(1.0), 0);
+        clock.request_step();
+        assert_eq!(clock.advance(1.0), 1);
+        assert_eq!(clock.advance(1.0), 0);
+        assert!((clock.time() - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slow_frames_are_capped() {
+        let mut clock = SimulationClock::new(0.01);
+        assert_eq!(clock.advance(100.0), MAX_STEPS_PER_FRAME);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+}
@@ -0,0 +1,182 @@
+use nalgebra_glm::{dot, Vec3};
+
+use super::model::{Model, Planet};
+use super::ship::Ship;
+
+// Hits slower than this along the surface normal land instead of bouncing
+const REST_SPEED: f32 = 1.0;
+
+/// Two models whose collision spheres overlap.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub a: usize,      // Index into `Scene::models`, always lower than `b`
+    pub b: usize,
+    pub normal: Vec3,  // Unit vector pointing from `b` towards `a`
+    pub depth: f32,    // How far the spheres overlap
+    pub bounced: bool, // Set when the response made `a` bounce off `b` rather than rest on it
+}
+
+/// Sphere-sphere test between every pair of models. Models with a zero radius never collide.
+pub fn detect_collisions(models: &[Box<dyn Model>]) -> Vec<CollisionEvent> {
+    let mut events = Vec::new();
+
+    for a in 0..models.len() {
+        let radius_a = models[a].get_colision_radius();
+        if radius_a <= 0.0 {
+            continue;
+        }
+
+        for b in (a + 1)..models.len() {
+            let radius_b = models[b].get_colision_radius();
+            if radius_b <= 0.0 {
+                continue;
+            }
+
+            let offset = models[a].get_position() - models[b].get_position();
+            let distance = offset.norm();
+            let depth = radius_a + radius_b - distance;
+            if depth > 0.0 {
+                // Concentric spheres have no meaningful direction, push upwards
+                let normal = if distance > 0.0 { offset / distance } else { Vec3::y() };
+                events.push(CollisionEvent { a, b, normal, depth, bounced: false });
+            }
+        }
+    }
+
+    events
+}
+
+/// Pushes the ship, always the first model, out of every body it overlaps and bounces it off
+/// or lands it on them. Sets `bounced` on the events where it bounced.
+pub fn resolve_ship_collisions(models: &mut [Box<dyn Model>], events: &mut [CollisionEvent], delta_time: f32) {
+    let Some((ship, bodies)) = models.split_first_mut() else {
+        return;
+    };
+    let Some(ship) = ship.as_any_mut().downcast_mut::<Ship>() else {
+        return;
+    };
+
+    for event in events.iter_mut().filter(|event| event.a == 0) {
+        let surface_velocity = bodies[event.b - 1]
+            .as_any()
+            .downcast_ref::<Planet>()
+            .map_or_else(Vec3::zeros, |planet| planet.velocity);
+
+        ship.position += event.normal * event.depth;
+
+        let contact = respond(
+            ship.velocity,
+            surface_velocity,
+            event.normal,
+            ship.restitution,
+            ship.friction,
+            REST_SPEED,
+            delta_time,
+        );
+        ship.velocity = contact.velocity;
+        event.bounced = contact.bounced;
+    }
+}
+
+/// How a body reacts when it hits a surface.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub velocity: Vec3, // Velocity after the hit
+    pub bounced: bool,
+}
+
+/// Resolves a moving body against a surface moving at `surface_velocity`.
+/// Fast hits bounce with `restitution`, slow ones come to rest and are dragged along by friction.
+pub fn respond(
+    velocity: Vec3,
+    surface_velocity: Vec3,
+    normal: Vec3,
+    restitution: f32,
+    friction: f32,
+    rest_speed: f32,
+    delta_time: f32,
+) -> Contact {
+    let relative = velocity - surface_velocity;
+    let approach = dot(&relative, &normal);
+
+    // Already separating, nothing to do
+    if approach >= 0.0 {
+        return Contact { velocity, bounced: false };
+    }
+
+    let tangent = relative - normal * approach;
+    let bounced = -approach > rest_speed;
+    let relative = if bounced {
+        tangent - normal * (approach * restitution)
+    } else {
+        tangent * (-friction * delta_time).exp()
+    };
+
+    Contact {
+        velocity: surface_velocity + relative,
+        bounced,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use super::super::orbit::Orbit;
+    use super::super::shader::SimpleShader;
+
+    fn body(position: Vec3, collision_radius: f32) -> Box<dyn Model> {
+        let orbit = Orbit {
+            semi_major_axis: 0.0,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            periapsis_argument: 0.0,
+            mean_anomaly: 0.0,
+            mean_motion: 0.0,
+        };
+        Box::new(Planet::new(Arc::new(Vec::new()), 1.0, Box::new(SimpleShader), orbit, collision_radius, position, 4))
+    }
+
+    #[test]
+    fn overlapping_spheres_collide() {
+        let models = vec![body(Vec3::zeros(), 1.0), body(Vec3::new(1.5, 0.0, 0.0), 1.0), body(Vec3::new(5.0, 0.0, 0.0), 1.0)];
+        let events = detect_collisions(&models);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].a, events[0].b), (0, 1));
+        assert!((events[0].depth - 0.5).abs() < 1e-6);
+        assert!((events[0].normal - Vec3::new(-1.0, 0.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn zero_radius_never_collides() {
+        let models = vec![body(Vec3::zeros(), 0.0), body(Vec3::zeros(), 1.0)];
+        assert!(detect_collisions(&models).is_empty());
+    }
+
+    #[test]
+    fn fast_hit_bounces() {
+        let contact = respond(Vec3::new(0.0, -10.0, 0.0), Vec3::zeros(), Vec3::y(), 0.5, 1.0, 1.0, 0.1);
+        assert!(contact.bounced);
+        assert!((contact.velocity - Vec3::new(0.0, 5.0, 0.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn slow_hit_lands_and_is_dragged_along() {
+        let surface = Vec3::new(2.0, 0.0, 0.0);
+        let contact = respond(Vec3::new(0.0, -0.5, 0.0), surface, Vec3::y(), 0.5, 1.0, 1.0, 0.1);
+        assert!(!contact.bounced);
+        assert!(contact.velocity.y.abs() < 1e-6);
+        // Friction pulls the ship towards the surface's velocity without overshooting it
+        assert!(contact.velocity.x > 0.0 && contact.velocity.x < surface.x);
+    }
+
+    #[test]
+    fn separating_body_is_left_alone() {
+        let velocity = Vec3::new(1.0, 3.0, 0.0);
+        let contact = respond(velocity, Vec3::zeros(), Vec3::y(), 0.5, 1.0, 1.0, 0.1);
+        assert!(!contact.bounced);
+        assert_eq!(contact.velocity, velocity);
+    }
+}
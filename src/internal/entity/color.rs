@@ -1,24 +1,28 @@
 use std::ops::Add;
 use std::ops::Mul;
 use std::fmt;
+use std::sync::OnceLock;
+
+use nalgebra_glm::Vec3;
 
 #[derive (Debug, Copy, Clone, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    pub a: u8, // Opacity, 255 is opaque. Only blending reads it, the framebuffer stores RGB
 }
 
 impl Color {
     pub fn new(r: u8, g: u8, b: u8) -> Color {
-        Color { r, g, b }
+        Color { r, g, b, a: 255 }
     }
 
     pub fn from_hex(hex: u32) -> Color {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8; // fixed this line
-        Color { r, g, b }
+        Color { r, g, b, a: 255 }
     }
 
     pub fn to_hex(&self) -> u32 {
@@ -26,7 +30,30 @@ impl Color {
     }
 
     pub fn black() -> Color {
-        Color {r: 0, g: 0, b: 0}
+        Color {r: 0, g: 0, b: 0, a: 255}
+    }
+
+    /// The same color with its opacity replaced, `alpha` in [0, 1].
+    pub fn with_alpha(&self, alpha: f32) -> Color {
+        Color { a: (alpha * 255.0).clamp(0.0, 255.0) as u8, ..*self }
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.a as f32 / 255.0
+    }
+
+    /// Linear light for this sRGB color, what lighting and blending should add up.
+    pub fn to_linear(self) -> Vec3 {
+        // Decoding is a lookup, it runs for every fragment
+        static DECODE: OnceLock<[f32; 256]> = OnceLock::new();
+        let table = DECODE.get_or_init(|| std::array::from_fn(|value| srgb_to_linear(value as f32 / 255.0)));
+        Vec3::new(table[self.r as usize], table[self.g as usize], table[self.b as usize])
+    }
+
+    /// The opaque sRGB color for linear light, channels above 1 are clipped.
+    pub fn from_linear(linear: &Vec3) -> Color {
+        let encode = |value: f32| (linear_to_srgb(value.clamp(0.0, 1.0)) * 255.0 + 0.5) as u8;
+        Color::new(encode(linear.x), encode(linear.y), encode(linear.z))
     }
     
     // Linear interpolation for Color
@@ -34,50 +61,34 @@ impl Color {
         let r = self.r as f32 + (other.r as f32 - self.r as f32) * t;
         let g = self.g as f32 + (other.g as f32 - self.g as f32) * t;
         let b = self.b as f32 + (other.b as f32 - self.b as f32) * t;
+        let a = self.a as f32 + (other.a as f32 - self.a as f32) * t;
         Color {
             r: r.clamp(0.0, 255.0) as u8,
             g: g.clamp(0.0, 255.0) as u8,
             b: b.clamp(0.0, 255.0) as u8,
+            a: a.clamp(0.0, 255.0) as u8,
         }
     }
 
     pub fn is_black(&self) -> bool {
         self.r == 0 && self.g == 0 && self.b == 0
     }
+}
 
-    // New blend mode methods
-    pub fn blend_normal(&self, blend: &Color) -> Color {
-        if blend.is_black() { *self } else { *blend }
-    }
-
-    pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8
-        )
-    }
-
-    pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8
-        )
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
     }
+}
 
-    
-  pub fn blend_subtract(&self, blend: &Color) -> Color {
-    if blend.is_black() {
-      *self
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
     } else {
-      Color::new(
-        self.r.saturating_sub(blend.r),
-        self.g.saturating_sub(blend.g),
-        self.b.saturating_sub(blend.b)
-      )
+        1.055 * value.powf(1.0 / 2.4) - 0.055
     }
-  }
 }
 
 impl Add for Color {
@@ -87,7 +98,7 @@ impl Add for Color {
         let r = self.r.saturating_add(other.r);
         let g = self.g.saturating_add(other.g);
         let b = self.b.saturating_add(other.b);
-        Color { r, g, b }
+        Color { r, g, b, a: self.a }
     }
 }
 
@@ -98,12 +109,37 @@ impl Mul<f32> for Color {
         let r = (self.r as f32 * factor).clamp(0.0, 255.0) as u8;
         let g = (self.g as f32 * factor).clamp(0.0, 255.0) as u8;
         let b = (self.b as f32 * factor).clamp(0.0, 255.0) as u8;
-        Color { r, g, b }
+        Color { r, g, b, a: self.a }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_survives_a_trip_through_linear() {
+        for value in 0..=255u8 {
+            let color = Color::new(value, 255 - value, value / 2);
+            assert_eq!(Color::from_linear(&color.to_linear()), color);
+        }
+    }
+
+    #[test]
+    fn decoding_matches_the_srgb_curve() {
+        assert_eq!(Color::black().to_linear(), Vec3::zeros());
+        assert_eq!(Color::new(255, 255, 255).to_linear(), Vec3::repeat(1.0));
+        // Mid grey in sRGB is only about a fifth of the light
+        assert!((Color::new(128, 128, 128).to_linear().x - 0.2158).abs() < 1e-3);
+    }
+
+    #[test]
+    fn encoding_clips_light_above_white() {
+        assert_eq!(Color::from_linear(&Vec3::new(4.0, -1.0, 1.0)), Color::new(255, 0, 255));
+    }
+}
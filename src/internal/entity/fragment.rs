@@ -7,18 +7,22 @@ pub struct Fragment {
     pub position: Vec2,
     pub color: Color,
     pub depth: f32,
-    pub intensity: f32,
-    pub vertex_position: Vec3,
+    pub vertex_position: Vec3,  // Model space position, used by shaders for surface patterns
+    pub world_position: Vec3,
+    pub normal: Vec3,           // World space normal
+    pub tex_coords: Vec2,
 }
 
 impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32, intensity: f32, vertex_position: Vec3) -> Self {
+    pub fn new(x: f32, y: f32, color: Color, depth: f32) -> Self {
         Fragment {
             position: Vec2::new(x, y),
             color,
             depth,
-            intensity,
-            vertex_position
+            vertex_position: Vec3::zeros(),
+            world_position: Vec3::zeros(),
+            normal: Vec3::zeros(),
+            tex_coords: Vec2::zeros(),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,66 @@
+use nalgebra_glm::Vec3;
+use super::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Emits in every direction from a world space position, like the sun.
+    /// `radius` is the size of the glowing body, it softens the edges of the shadows it casts.
+    Point { position: Vec3, radius: f32 },
+    /// Parallel rays travelling along `direction`, for far away sources.
+    Directional { direction: Vec3 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn point(position: Vec3, radius: f32, color: Color, intensity: f32) -> Self {
+        Light { kind: LightKind::Point { position, radius }, color, intensity }
+    }
+
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light { kind: LightKind::Directional { direction: direction.normalize() }, color, intensity }
+    }
+
+    /// Unit vector pointing from `position` towards the light.
+    pub fn direction_from(&self, position: &Vec3) -> Vec3 {
+        match self.kind {
+            LightKind::Point { position: light_position, .. } => (light_position - position).normalize(),
+            LightKind::Directional { direction } => -direction,
+        }
+    }
+
+    /// How far the light is from `position` and how big it looks from there, as an angular radius.
+    /// Directional lights are infinitely far away and appear as a point.
+    pub fn source_from(&self, position: &Vec3) -> (f32, f32) {
+        match self.kind {
+            LightKind::Point { position: light_position, radius } => {
+                let distance = (light_position - position).norm();
+                let angular_radius = if distance > radius { (radius / distance).asin() } else { std::f32::consts::FRAC_PI_2 };
+                (distance, angular_radius)
+            }
+            LightKind::Directional { .. } => (f32::INFINITY, 0.0),
+        }
+    }
+
+    /// Lambert diffuse and Blinn-Phong specular factors for a surface point, both
+    /// already scaled by the light intensity. `view_dir` points from the surface to the eye.
+    pub fn illuminate(&self, position: &Vec3, normal: &Vec3, view_dir: &Vec3, shininess: f32) -> (f32, f32) {
+        let light_dir = self.direction_from(position);
+        let lambert = normal.dot(&light_dir);
+
+        // Surfaces facing away from the light get neither diffuse nor a highlight
+        if lambert <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let half_vector = (light_dir + view_dir).normalize();
+        let specular = normal.dot(&half_vector).max(0.0).powf(shininess);
+
+        (lambert * self.intensity, specular * self.intensity)
+    }
+}
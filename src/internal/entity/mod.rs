@@ -1,4 +1,5 @@
 pub mod vertex;
 pub mod color;
 pub mod skybox;
-pub mod fragment;
\ No newline at end of file
+pub mod fragment;
+pub mod light;
@@ -48,10 +48,10 @@ impl Skybox {
     /// - `width`: Width of the framebuffer.
     /// - `height`: Height of the framebuffer.
     pub fn render(&self, framebuffer: &mut Framebuffer, perspective_matrix: &Mat4, view_matrix: &Mat4) {
-        let background_color = self.space_color.to_hex();
-        let star_color = self.stars_color.to_hex();
+        let background_color = self.space_color.to_linear();
+        let star_color = self.stars_color.to_linear();
         // Fill the background with space_color
-        for pixel in framebuffer.buffer.iter_mut() {
+        for pixel in framebuffer.hdr_buffer.iter_mut() {
             *pixel = background_color;
         }
         // Combine view and projection matrices
@@ -74,7 +74,7 @@ impl Skybox {
                 // Ensure the star is within the framebuffer bounds
                 if screen_x >= 0 && screen_x < framebuffer.width as isize && screen_y >= 0 && screen_y < framebuffer.height as isize {
                     let index = screen_y as usize * framebuffer.width + screen_x as usize;
-                    framebuffer.buffer[index] = star_color;
+                    framebuffer.hdr_buffer[index] = star_color;
                 }
             }
         }
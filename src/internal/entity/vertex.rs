@@ -7,9 +7,11 @@ pub struct Vertex {
   pub normal: Vec3,
   pub tex_coords: Vec2,
   pub color: Color,
+  pub clip_position: Vec4,
   pub frustrum_position: Vec4,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub world_position: Vec3,
 }
 
 impl Vertex {
@@ -19,9 +21,11 @@ impl Vertex {
       normal,
       tex_coords,
       color: Color::black(),
+      clip_position: frustrum_position,
       frustrum_position,
       transformed_position: position,
       transformed_normal: normal,
+      world_position: position,
     }
   }
 
@@ -31,9 +35,27 @@ impl Vertex {
       normal,
       tex_coords,
       color,
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 0.0),
       frustrum_position: Vec4::new(0.0, 0.0, 0.0, 0.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+    }
+  }
+
+  /// Linearly interpolates every attribute towards `other`, used to create the new
+  /// vertices where an edge crosses a clipping plane.
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      clip_position: self.clip_position.lerp(&other.clip_position, t),
+      frustrum_position: self.frustrum_position.lerp(&other.frustrum_position, t),
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+      world_position: self.world_position.lerp(&other.world_position, t),
     }
   }
 
@@ -50,9 +72,11 @@ impl Default for Vertex {
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 0.0),
       frustrum_position: Vec4::new(0.0, 0.0, 0.0, 0.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
     }
   }
 }
\ No newline at end of file
@@ -3,17 +3,49 @@ use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
 
+use nalgebra_glm::{Vec3, Vec4};
+use rayon::prelude::*;
+
 use crate::internal::entity::color::Color;
+use crate::internal::material::Material;
+use crate::internal::tone_mapping::ToneMapping;
 
 pub struct Framebuffer {
     pub width : usize, 
     pub height: usize,
-    pub buffer : Vec<u32>,
+    pub buffer : Vec<u32>,      // Displayable sRGB pixels, filled from `hdr_buffer` by `resolve`
+    pub hdr_buffer : Vec<Vec3>, // Linear light the frame is drawn into, channels can go past 1
     pub zbuffer : Vec<f32>,
     background_color : Color,
     current_color : Color
 }
 
+/// A horizontal band of rows `y_start..y_end` borrowed mutably from a `Framebuffer`,
+/// so several threads can draw into disjoint parts of the same frame.
+pub struct FramebufferTile<'a> {
+    pub y_start: usize,
+    pub y_end: usize,
+    width: usize,
+    hdr_buffer: &'a mut [Vec3],
+    zbuffer: &'a mut [f32],
+}
+
+impl FramebufferTile<'_> {
+    // Same as `Framebuffer::blend_point`, with (x, y) in framebuffer coordinates
+    pub fn blend_point(&mut self, x: usize, y: usize, depth: f32, color: &Vec4, material: &Material) {
+        if x < self.width && (self.y_start..self.y_end).contains(&y) {
+            let index = (y - self.y_start) * self.width + x;
+
+            if self.zbuffer[index] > depth {
+                self.hdr_buffer[index] = material.blend.apply(&self.hdr_buffer[index], color);
+                if material.depth_write {
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+}
+
 pub trait RenderableToFile {
     fn render_buffer(&self, filename: &str) -> io::Result<()>;
     fn write_bmp_header(&self, file: &mut File) -> io::Result<()>;
@@ -25,11 +57,13 @@ impl Framebuffer {
     pub fn new(width: usize, height: usize, background_color: Color ) -> Self {
         let buffer_size = width * height;
         let buffer = vec![background_color.to_hex(); buffer_size]; // Initialize buffer with background color
+        let hdr_buffer = vec![background_color.to_linear(); buffer_size];
         let zbuffer = vec![f32::INFINITY; buffer_size]; // Initialize buffer with background color
         Framebuffer {
             width,
             height,
             buffer,
+            hdr_buffer,
             zbuffer,
             background_color,
             current_color: Color::new(0, 0, 0), // Default current color to black
@@ -45,28 +79,74 @@ impl Framebuffer {
     // Function to clear the framebuffer with the background color
     pub fn clear(&mut self) {
         let background_hex = self.background_color.to_hex();
+        let background_linear = self.background_color.to_linear();
         for i in 0..self.buffer.len() {
             self.buffer[i] = background_hex;
+            self.hdr_buffer[i] = background_linear;
             self.zbuffer[i] = INFINITY;
         }
     }
 
     // Function to draw a point at (x, y) using the current color
     pub fn draw_point(&mut self, x: usize, y: usize, depth: f32) {
-        if  0 < x  
-            && x < self.width 
-            && 0 < y 
-            && y < self.height {
+        if x < self.width && y < self.height {
 
             let index = y * self.width + x;
 
             if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color.to_hex();
+                self.hdr_buffer[index] = self.current_color.to_linear();
                 self.zbuffer[index] = depth;
             }
         }
     }
 
+    /// Blends `color` (linear RGB and alpha) into the pixel at (x, y) if it passes the depth test,
+    /// writing the depth only when the material asks for it.
+    pub fn blend_point(&mut self, x: usize, y: usize, depth: f32, color: &Vec4, material: &Material) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+
+            if self.zbuffer[index] > depth {
+                self.hdr_buffer[index] = material.blend.apply(&self.hdr_buffer[index], color);
+                if material.depth_write {
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+
+    /// Splits the framebuffer into bands of `tile_height` rows that can be drawn in parallel.
+    pub fn par_tiles_mut(&mut self, tile_height: usize) -> impl IndexedParallelIterator<Item = FramebufferTile<'_>> {
+        let width = self.width;
+        let chunk_size = width * tile_height;
+
+        self.hdr_buffer
+            .par_chunks_mut(chunk_size)
+            .zip(self.zbuffer.par_chunks_mut(chunk_size))
+            .enumerate()
+            .map(move |(index, (hdr_buffer, zbuffer))| {
+                let y_start = index * tile_height;
+                FramebufferTile {
+                    y_start,
+                    y_end: y_start + hdr_buffer.len() / width,
+                    width,
+                    hdr_buffer,
+                    zbuffer,
+                }
+            })
+    }
+
+    /// Turns the HDR frame into displayable pixels: scales it by `exposure`, tone maps it
+    /// and encodes it as sRGB into `buffer`.
+    pub fn resolve(&mut self, exposure: f32, tone_mapping: ToneMapping) {
+        self.buffer
+            .par_iter_mut()
+            .zip(self.hdr_buffer.par_iter())
+            .for_each(|(pixel, light)| {
+                *pixel = Color::from_linear(&tone_mapping.apply(&(light * exposure))).to_hex();
+            });
+    }
+
     // Function to draw a point at (x, y) using the current color
     /// owo
     pub fn get_point_color(&mut self, x: usize, y: usize) -> Color{
@@ -150,7 +230,7 @@ impl RenderableToFile for Framebuffer {
 }
 
 fn write_pixel_data(&self, file: &mut File) -> io::Result<()> {
-    for y in 0..self.height { // BMP files are bottom to top
+    for y in (0..self.height).rev() { // BMP files are bottom to top
         for x in 0..self.width {
             let index = y * self.width + x;
             let color_hex = self.buffer[index];
@@ -0,0 +1,206 @@
+use nalgebra_glm::Vec3;
+
+// Never run more than this many sub-steps in one update, so a long hitch can't stall the frame loop
+const MAX_SUBSTEPS_PER_UPDATE: usize = 1000;
+
+/// A body moved by gravity instead of its scripted orbit.
+#[derive(Clone, Debug)]
+pub struct GravityBody {
+    pub model: usize, // Index into `Scene::models`
+    pub mass: f32,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    acceleration: Vec3,
+}
+
+impl GravityBody {
+    pub fn new(model: usize, mass: f32, position: Vec3, velocity: Vec3) -> Self {
+        GravityBody {
+            model,
+            mass,
+            position,
+            velocity,
+            acceleration: Vec3::zeros(),
+        }
+    }
+}
+
+/// Integrates mutual gravity between bodies with velocity Verlet at a fixed sub-step.
+/// Verlet is symplectic, so orbits stay closed and the total energy only wobbles instead of drifting away.
+#[derive(Clone)]
+pub struct GravitySimulation {
+    pub bodies: Vec<GravityBody>,
+    pub constant: f32,  // Gravitational constant G in scene units
+    pub substep: f32,   // Fixed integration step, independent of the frame rate
+    pub softening: f32, // Keeps the force finite when two bodies get very close
+    accumulator: f32,
+    initial_energy: f32,
+}
+
+impl GravitySimulation {
+    pub fn new(bodies: Vec<GravityBody>, constant: f32, substep: f32, softening: f32) -> Self {
+        let mut simulation = GravitySimulation {
+            bodies,
+            constant,
+            substep,
+            softening,
+            accumulator: 0.0,
+            initial_energy: 0.0,
+        };
+        simulation.update_accelerations();
+        simulation.initial_energy = simulation.total_energy();
+        simulation
+    }
+
+    /// Where the simulation has moved `model`, `None` if it follows its scripted orbit.
+    pub fn position_of(&self, model: usize) -> Option<Vec3> {
+        self.bodies.iter().find(|body| body.model == model).map(|body| body.position)
+    }
+
+    /// Pull of every body on a massless probe at `position`, e.g. the ship.
+    pub fn acceleration_at(&self, position: &Vec3) -> Vec3 {
+        let softening = self.softening * self.softening;
+        self.bodies.iter().fold(Vec3::zeros(), |acceleration, body| {
+            let offset = body.position - position;
+            let distance_squared = offset.norm_squared() + softening;
+            acceleration + offset * (self.constant * body.mass / (distance_squared * distance_squared.sqrt()))
+        })
+    }
+
+    /// Advances the simulation by `delta_time`, carrying leftover time over to the next call.
+    /// Verlet is time reversible, so a negative `delta_time` runs the system backwards.
+    pub fn advance(&mut self, delta_time: f32) {
+        let direction = if delta_time < 0.0 { -1.0 } else { 1.0 };
+        if direction * self.accumulator < 0.0 {
+            self.accumulator = 0.0;
+        }
+        self.accumulator += delta_time;
+
+        let mut steps = 0;
+        while self.accumulator * direction >= self.substep && steps < MAX_SUBSTEPS_PER_UPDATE {
+            self.step(self.substep * direction);
+            self.accumulator -= self.substep * direction;
+            steps += 1;
+        }
+
+        if steps == MAX_SUBSTEPS_PER_UPDATE {
+            self.accumulator = 0.0;
+        }
+    }
+
+    /// One velocity Verlet step: drift with the current acceleration, then average old and new forces.
+    pub fn step(&mut self, h: f32) {
+        for body in self.bodies.iter_mut() {
+            body.position += body.velocity * h + body.acceleration * (0.5 * h * h);
+        }
+
+        let previous: Vec<Vec3> = self.bodies.iter().map(|body| body.acceleration).collect();
+        self.update_accelerations();
+
+        for (body, previous) in self.bodies.iter_mut().zip(previous) {
+            body.velocity += (previous + body.acceleration) * (0.5 * h);
+        }
+    }
+
+    fn update_accelerations(&mut self) {
+        let softening = self.softening * self.softening;
+
+        for body in self.bodies.iter_mut() {
+            body.acceleration = Vec3::zeros();
+        }
+
+        for i in 0..self.bodies.len() {
+            for j in (i + 1)..self.bodies.len() {
+                let offset = self.bodies[j].position - self.bodies[i].position;
+                let distance_squared = offset.norm_squared() + softening;
+                let pull = offset * (self.constant / (distance_squared * distance_squared.sqrt()));
+
+                let (mass_i, mass_j) = (self.bodies[i].mass, self.bodies[j].mass);
+                self.bodies[i].acceleration += pull * mass_j;
+                self.bodies[j].acceleration -= pull * mass_i;
+            }
+        }
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.bodies.iter().map(|body| 0.5 * body.mass * body.velocity.norm_squared()).sum()
+    }
+
+    pub fn potential_energy(&self) -> f32 {
+        let softening = self.softening * self.softening;
+        let mut energy = 0.0;
+
+        for i in 0..self.bodies.len() {
+            for j in (i + 1)..self.bodies.len() {
+                let distance = ((self.bodies[j].position - self.bodies[i].position).norm_squared() + softening).sqrt();
+                energy -= self.constant * self.bodies[i].mass * self.bodies[j].mass / distance;
+            }
+        }
+        energy
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Relative change of the total energy since the simulation started, a good integrator keeps it tiny.
+    pub fn energy_drift(&self) -> f32 {
+        if self.initial_energy == 0.0 {
+            return 0.0;
+        }
+        (self.total_energy() - self.initial_energy) / self.initial_energy.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A light planet on a circular orbit around a heavy star
+    fn two_bodies() -> GravitySimulation {
+        let speed = (1000.0f32 / 10.0).sqrt();
+        GravitySimulation::new(
+            vec![
+                GravityBody::new(0, 1000.0, Vec3::zeros(), Vec3::new(0.0, 0.0, -speed / 1000.0)),
+                GravityBody::new(1, 1.0, Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 0.0, speed)),
+            ],
+            1.0,
+            0.001,
+            0.01,
+        )
+    }
+
+    #[test]
+    fn energy_drift_stays_bounded() {
+        let mut simulation = two_bodies();
+        // About three full orbits
+        for _ in 0..20 {
+            simulation.advance(1.0);
+            assert!(simulation.energy_drift().abs() < 1e-3, "drift {}", simulation.energy_drift());
+        }
+    }
+
+    #[test]
+    fn circular_orbit_keeps_its_radius() {
+        let mut simulation = two_bodies();
+        simulation.advance(5.0);
+        let distance = (simulation.bodies[1].position - simulation.bodies[0].position).norm();
+        assert!((distance - 10.0).abs() < 0.1, "distance {}", distance);
+    }
+
+    #[test]
+    fn running_backwards_returns_to_the_start() {
+        let mut simulation = two_bodies();
+        simulation.advance(2.0);
+        simulation.advance(-2.0);
+        assert!((simulation.bodies[1].position - Vec3::new(10.0, 0.0, 0.0)).norm() < 0.05);
+    }
+
+    #[test]
+    fn probe_feels_the_same_pull_as_a_body() {
+        let simulation = two_bodies();
+        let acceleration = simulation.acceleration_at(&Vec3::new(10.0, 0.0, 0.0));
+        let expected = simulation.bodies[1].acceleration;
+        assert!((acceleration - expected).norm() < 1e-4);
+    }
+}
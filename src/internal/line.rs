@@ -31,7 +31,7 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
         let depth = a.transformed_position.z * (1.0 - t) + b.transformed_position.z * t;
 
         // Create a fragment at this point
-        fragments.push(Fragment::new(x as f32, y as f32, color, depth, 1.0, Vec3::zeros()));
+        fragments.push(Fragment::new(x as f32, y as f32, color, depth));
 
         // Bresenham's decision
         let e2 = 2 * err;
@@ -58,31 +58,32 @@ pub fn triangle_wireframe(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment
     fragments
 }
 
-pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, camera_dir: Vec3) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
-     
+/// Rasterizes a triangle, handing every covered pixel inside `bounds` (min_x, min_y, max_x, max_y,
+/// inclusive) to `emit` in scanline order.
+pub fn triangle_flat_shade(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    bounds: (i32, i32, i32, i32),
+    mut emit: impl FnMut(Fragment),
+) {
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
-    
-    // Compute two edge vectors
-    let edge1 = v2.position - v1.position;
-    let edge2 = v3.position - v1.position;
+    let triangle_area = edge_function(&a, &b, &c);
 
-    // Compute the cross product of the edge vectors to get the normal
-    let normal = edge1.cross(&edge2).normalize();
-    
-    // Calculate the center of the triangle
-    let center = (a + b + c) / 3.0;
-    
-    // Calculate the light direction from the origin (0, 0, 0) to the triangle's center
-    let light_dir = center.normalize(); // Light comes from the origin (0, 0, 0)
-    
-    // Perform back-face culling: skip triangles facing away from the camera
-    if normal.dot(&camera_dir) > 0.0 {
-        return fragments; // Skip rendering this triangle
+    // Perform back-face culling on screen: a triangle facing away from the camera winds the other way.
+    // This holds wherever it is on screen and however its model is rotated
+    if triangle_area <= 0.0 {
+        return; // Skip rendering this triangle
     }
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-    let triangle_area = edge_function(&a, &b, &c);
+    let (min_x, min_y) = (min_x.max(bounds.0), min_y.max(bounds.1));
+    let (max_x, max_y) = (max_x.min(bounds.2), max_y.min(bounds.3));
+    let (inv_w1, inv_w2, inv_w3) = (
+        1.0 / v1.clip_position.w,
+        1.0 / v2.clip_position.w,
+        1.0 / v3.clip_position.w,
+    );
 
     // Iterate over each pixel in the bounding box
     for y in min_y..=max_y {
@@ -96,34 +97,37 @@ pub fn triangle_flat_shade(v1: &Vertex, v2: &Vertex, v3: &Vertex, camera_dir: Ve
             if w1 >= 0.0 && w1 <= 1.0 && 
                w2 >= 0.0 && w2 <= 1.0 &&
                w3 >= 0.0 && w3 <= 1.0 {
-                // Interpolate normal
-                let normal = v1.normal * w1 + v2.normal * w2 + v3.normal * w3;
-                let normal = normal.normalize();
+                // Perspective-correct weights: attributes are affine in clip space, not on screen,
+                // so weight each vertex by 1/w and renormalize
+                let (p1, p2, p3) = (w1 * inv_w1, w2 * inv_w2, w3 * inv_w3);
+                let inv_sum = 1.0 / (p1 + p2 + p3);
+                let (p1, p2, p3) = (p1 * inv_sum, p2 * inv_sum, p3 * inv_sum);
 
-                // Calculate lighting intensity
-                let intensity = normal.dot(&light_dir).max(0.05);
+                // Interpolate the world space normal and position for lighting
+                let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
+                let world_position = v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3;
 
                 // Create a gray color and apply lighting
-                let base_color = v1.color.clone(); // Medium gray
+                let base_color = v1.color; // Medium gray
                                                    
                 // Positions of the original vertex
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+                let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+
+                let tex_coords = v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3;
 
-                // Interpolate depth
+                // Depth is z/w, which is already affine in screen space
                 let depth = a.z * w1 + b.z * w2 + c.z * w3;
 
-                fragments.push(Fragment::new(
-                    point.x as f32, 
-                    point.y as f32, 
-                    base_color, 
-                    depth, 
-                    intensity, 
-                    vertex_position));
+                let mut fragment = Fragment::new(point.x, point.y, base_color, depth);
+                fragment.vertex_position = vertex_position;
+                fragment.world_position = world_position;
+                fragment.normal = normal.normalize();
+                fragment.tex_coords = tex_coords;
+
+                emit(fragment);
             }
         }
     }
-
-    fragments
 }
 
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
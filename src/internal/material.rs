@@ -0,0 +1,51 @@
+use nalgebra_glm::{Vec3, Vec4};
+use serde::Deserialize;
+
+/// How a fragment's color combines with the pixel already in the framebuffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    /// Replaces the pixel and ignores alpha.
+    #[default]
+    Opaque,
+    /// Paints over the pixel by the fragment's alpha, for clouds and rings.
+    Alpha,
+    /// Adds the fragment's light, for glows like atmospheres.
+    Additive,
+    /// Darkens the pixel, for tinted glass and dust.
+    Multiply,
+}
+
+impl BlendMode {
+    /// The light left in the framebuffer when `source` (linear RGB and alpha) is drawn over
+    /// `destination`. Blending linear light keeps glows and translucent edges from darkening.
+    pub fn apply(&self, destination: &Vec3, source: &Vec4) -> Vec3 {
+        let (color, alpha) = (source.xyz(), source.w);
+        match self {
+            BlendMode::Opaque => color,
+            BlendMode::Alpha => destination.lerp(&color, alpha),
+            BlendMode::Additive => destination + color * alpha,
+            BlendMode::Multiply => destination.component_mul(&Vec3::repeat(1.0).lerp(&color, alpha)),
+        }
+    }
+}
+
+/// Surface settings for how a model is written into the framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Material {
+    pub blend: BlendMode,
+    pub depth_write: bool, // Off lets models drawn later show through this one
+}
+
+impl Material {
+    /// Translucent materials are drawn after every opaque one, furthest first.
+    pub fn is_transparent(&self) -> bool {
+        self.blend != BlendMode::Opaque
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material { blend: BlendMode::Opaque, depth_write: true }
+    }
+}
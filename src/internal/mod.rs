@@ -1,8 +1,25 @@
 pub mod framebuffer;
 pub mod entity;
 pub mod line;
+pub mod atmosphere;
 pub mod object;
 pub mod render;
 pub mod shader;
+pub mod material;
+pub mod tone_mapping;
 pub mod camera;
-pub mod model;
\ No newline at end of file
+pub mod camera_path;
+pub mod model;
+pub mod ship;
+pub mod scene;
+pub mod noise;
+pub mod clipping;
+pub mod orbit;
+pub mod gravity;
+pub mod collision;
+pub mod picking;
+pub mod post_process;
+pub mod shadow;
+pub mod clock;
+pub mod time_loop;
+pub mod shader_registry;
@@ -1,93 +1,58 @@
 use std::sync::Arc;
 use std::any::Any;
-use nalgebra_glm::{Vec2, Vec3,Vec4};
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
 
+use super::atmosphere::Atmosphere;
 use super::entity::vertex::Vertex;
-use super::entity::fragment::Fragment;
-use super::render::Uniforms;
-use super::entity::color::Color;
+use super::material::Material;
+use super::orbit::Orbit;
+use super::render::create_model_matrix;
+use super::shader::FragmentShader;
+use super::shadow::MeshBounds;
 
 // Trait definition
 pub trait Model {
     fn get_vertex_array(&self) -> Arc<Vec<Vertex>>;
-    fn get_shader(&self) -> fn(&Fragment, &Uniforms) -> Color;
+    fn get_shader(&self) -> &dyn FragmentShader;
     fn get_position(&self) -> Vec3;
     fn set_position(&mut self, position: Vec3);
     fn get_scale(&self) -> f32;
-    fn get_rotation(&self) -> Vec3;
-    fn set_rotation(&mut self, rotation: Vec3);
     fn get_colision_radius(&self) -> f32;
+    fn get_bounds(&self) -> MeshBounds; // Fitted to the mesh once, when the model is built
+    fn get_model_matrix(&self) -> Mat4; // Local to world transform used for rendering
+    fn get_frame(&self) -> Mat4; // Translation and rotation without scale, children are placed in this frame
+    fn get_material(&self) -> Material { // Opaque unless the model says otherwise
+        Material::default()
+    }
     fn as_any(&self) -> &dyn Any; // Add this method
     fn as_any_mut(&mut self) -> &mut dyn Any; // Add this method for mutable access
 }
 
-// SimpleModel struct
-pub struct SimpleModel {
+// Planet struct
+pub struct Planet {
+    pub name: String,
     pub vertex_array: Arc<Vec<Vertex>>, // Change to Arc<Vec<Vertex>>
-    pub shader: fn(&Fragment, &Uniforms) -> Color,
-    pub position: Vec3,
+    pub shader: Box<dyn FragmentShader>,
+    pub position: Vec3,       // World space position, kept in sync by `translate`
     pub scale: f32,
-    pub rotation: Vec3,
+    pub rotation: Vec3,         // (0, spin angle, axial tilt), kept in sync by `rotate`
     pub collision_radius: f32,
-}
-
-// Implement the Model trait for SimpleModel
-impl Model for SimpleModel {
-    fn get_vertex_array(&self) -> Arc<Vec<Vertex>> {
-        Arc::clone(&self.vertex_array) // Clone the Arc to return a reference-counted version
-    }
-
-    fn get_shader(&self) -> fn(&Fragment, &Uniforms) -> Color {
-        self.shader
-    }
-
-    fn get_position(&self) -> Vec3 {
-        self.position
-    }
+    pub bounds: MeshBounds,
 
-    fn set_position(&mut self, position: Vec3){
-        self.position = position;
-    }
-
-    fn get_scale(&self) -> f32 {
-        self.scale
-    }
+    pub axial_tilt: f32,        // Radians the spin axis leans away from the orbit's up (+Y)
+    pub spin_period: f32,       // Seconds for one turn around the spin axis, negative spins backwards, 0 doesn't spin
 
-    fn get_rotation(&self) -> Vec3 {
-        self.rotation
-    }
+    pub parent: Option<usize>,  // Index of the model this body orbits around, if any
+    pub parent_frame: Mat4,     // The parent's `get_frame`, refreshed every update
+    pub local_position: Vec3,   // Position inside the parent's frame
+    pub velocity: Vec3,         // World space, measured from the last update
 
-    fn set_rotation(&mut self, rotation: Vec3){
-        self.rotation = rotation;
-    }
+    pub center: Vec3,           // Focus of the orbit inside the parent's frame
+    pub orbit: Orbit,
+    pub orbit_segments: Vec<Vertex>,
 
-    fn get_colision_radius(&self) -> f32 {
-        self.collision_radius
-    }
-    
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
-}
-
-// Planet struct
-pub struct Planet {
-    pub vertex_array: Arc<Vec<Vertex>>, // Change to Arc<Vec<Vertex>>
-    pub shader: fn(&Fragment, &Uniforms) -> Color,
-    pub position: Vec3,
-    pub scale: f32,
-    pub rotation: Vec3,
-    pub collision_radius: f32,
-
-    pub center: Vec3,
-    pub orbit_angle: f32,
-    pub orbit_speed: f32,
-    pub orbit_radius: f32,
-    pub orbit_segments: Vec<Vertex>
+    pub material: Material,
+    pub atmosphere: Option<Atmosphere>, // Glowing shell drawn around the body after everything else
 }
 
 // Implement the Model trait for Planet
@@ -96,8 +61,8 @@ impl Model for Planet {
         Arc::clone(&self.vertex_array) // Clone the Arc to return a reference-counted version
     }
 
-    fn get_shader(&self) -> fn(&Fragment, &Uniforms) -> Color {
-        self.shader
+    fn get_shader(&self) -> &dyn FragmentShader {
+        self.shader.as_ref()
     }
 
     fn get_position(&self) -> Vec3 {
@@ -106,22 +71,33 @@ impl Model for Planet {
 
     fn set_position(&mut self, position: Vec3){
         self.position = position;
+        let to_local = self.parent_frame.try_inverse().unwrap_or(Mat4::identity());
+        self.local_position = (to_local * position.push(1.0)).xyz();
     }
 
     fn get_scale(&self) -> f32 {
         self.scale
     }
 
-    fn get_rotation(&self) -> Vec3 {
-        self.rotation
+    fn get_colision_radius(&self) -> f32 {
+        self.collision_radius
     }
 
-    fn set_rotation(&mut self, rotation: Vec3){
-        self.rotation = rotation;
+    fn get_bounds(&self) -> MeshBounds {
+        self.bounds
     }
 
-    fn get_colision_radius(&self) -> f32 {
-        self.collision_radius
+    fn get_model_matrix(&self) -> Mat4 {
+        self.parent_frame * create_model_matrix(self.local_position, self.scale, self.rotation)
+    }
+
+    fn get_frame(&self) -> Mat4 {
+        // Children follow the tilt but not the spin, so moons don't whip around with the surface
+        self.parent_frame * create_model_matrix(self.local_position, 1.0, Vec3::new(0.0, 0.0, self.axial_tilt))
+    }
+
+    fn get_material(&self) -> Material {
+        self.material
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -139,62 +115,79 @@ impl Planet {
     pub fn new(
         vertex_array: Arc<Vec<Vertex>>,
         scale: f32,
-        shader: fn(&Fragment, &Uniforms) -> Color,
-        orbit_radius: f32,
-        orbit_angle: f32,
-        orbit_speed: f32,
+        shader: Box<dyn FragmentShader>,
+        orbit: Orbit,
         collision_radius: f32,
-        center: Vec3, // Center of the orbit
+        center: Vec3, // Focus of the orbit
         orbit_segments: usize, // Number of segments for the orbit
     ) -> Self {
         // Calculate initial position based on orbit parameters
-        let x = center.x + orbit_radius * orbit_angle.cos();
-        let z = center.z + orbit_radius * orbit_angle.sin();
-        let position = Vec3::new(x, center.y, z);
+        let local_position = center + orbit.position();
 
         let rotation = Vec3::new(0.0, 0.0, 0.0);
 
         // Generate orbit vertices
-        let orbit_vertices = create_orbit(orbit_radius, center, orbit_segments);
+        let orbit_vertices = create_orbit(&orbit, center, orbit_segments);
 
         Planet {
+            name: String::new(),
+            bounds: MeshBounds::fit(&vertex_array),
             vertex_array,
             shader,
-            position,
+            position: local_position,
             scale,
             rotation,
             collision_radius,
+            axial_tilt: 0.0,
+            spin_period: 0.0,
+            parent: None,
+            parent_frame: Mat4::identity(),
+            local_position,
+            velocity: Vec3::zeros(),
             center,
-            orbit_angle,
-            orbit_speed,
-            orbit_radius,
+            orbit,
             orbit_segments: orbit_vertices, // Initialize the orbit vertices
+            material: Material::default(),
+            atmosphere: None,
         }
     }
 
     pub fn translate(&mut self, delta_time: f32) {
-        // Update orbit angle based on orbit speed and time step
-        self.orbit_angle += self.orbit_speed * delta_time;
-
-        // Ensure the angle stays within the range [0, 2π] to prevent overflow
-        self.orbit_angle %= std::f32::consts::TAU;
+        // Move along the orbit and solve Kepler's equation for the new position
+        self.orbit.advance(delta_time);
+        self.local_position = self.center + self.orbit.position();
+
+        // Bring the local position into world space through the parent's frame
+        self.position = (self.parent_frame * self.local_position.push(1.0)).xyz();
+    }
+
+    /// Grows or shrinks the body, its collision sphere and atmosphere shell follow along.
+    pub fn resize(&mut self, scale: f32) {
+        let factor = scale / self.scale;
+        self.scale = scale;
+        self.collision_radius *= factor;
+        if let Some(atmosphere) = &mut self.atmosphere {
+            atmosphere.radius *= factor;
+            atmosphere.planet_radius *= factor;
+        }
+    }
 
-        // Recalculate position based on the updated orbit angle
-        let x = self.center.x + self.orbit_radius * self.orbit_angle.cos();
-        let z = self.center.z + self.orbit_radius * self.orbit_angle.sin();
-        self.position = Vec3::new(x, self.position.y, z);
+    pub fn rotate(&mut self, delta_time: f32) {
+        // Spin around the local Y axis, which the tilt then leans over
+        let mut spin = self.rotation.y;
+        if self.spin_period != 0.0 {
+            spin = (spin + std::f32::consts::TAU / self.spin_period * delta_time).rem_euclid(std::f32::consts::TAU);
+        }
+        self.rotation = Vec3::new(0.0, spin, self.axial_tilt);
     }
 
 }
 
-fn create_orbit(radius: f32, center: Vec3, segments: usize) -> Vec<Vertex> {
+fn create_orbit(orbit: &Orbit, center: Vec3, segments: usize) -> Vec<Vertex> {
     let mut orbit_vertices = Vec::with_capacity(segments);
 
-    for i in 0..segments {
-        let angle = 2.0 * std::f32::consts::PI * (i as f32 / segments as f32);
-        let x = center.x + radius * angle.cos();
-        let z = center.z + radius * angle.sin();
-        let position = Vec3::new(x, center.y, z);
+    for point in orbit.path(segments) {
+        let position = center + point;
 
         orbit_vertices.push(Vertex::new(
             position,
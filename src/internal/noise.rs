@@ -0,0 +1,94 @@
+use fastnoise_lite::{FastNoiseLite, FractalType, NoiseType};
+use serde::Deserialize;
+
+/// Noise algorithms available to shaders, named as in scene files.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseKind {
+    OpenSimplex2,
+    OpenSimplex2s,
+    Cellular,
+    Perlin,
+    ValueCubic,
+    Value,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FractalKind {
+    #[default]
+    None,
+    Fbm,
+    Ridged,
+    PingPong,
+}
+
+/// Everything needed to build one `FastNoiseLite` generator.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoiseConfig {
+    pub noise_type: NoiseKind,
+    pub frequency: f32,
+    #[serde(default = "default_seed")]
+    pub seed: i32,
+    #[serde(default)]
+    pub fractal_type: FractalKind,
+    #[serde(default = "default_octaves")]
+    pub octaves: i32,
+    #[serde(default = "default_ping_pong_strength")]
+    pub ping_pong_strength: f32,
+}
+
+// Same defaults as FastNoiseLite::new()
+fn default_seed() -> i32 {
+    1337
+}
+
+fn default_octaves() -> i32 {
+    3
+}
+
+fn default_ping_pong_strength() -> f32 {
+    2.0
+}
+
+impl NoiseConfig {
+    pub fn new(noise_type: NoiseKind, frequency: f32) -> Self {
+        NoiseConfig {
+            noise_type,
+            frequency,
+            seed: default_seed(),
+            fractal_type: FractalKind::None,
+            octaves: default_octaves(),
+            ping_pong_strength: default_ping_pong_strength(),
+        }
+    }
+
+    pub fn with_fractal(mut self, fractal_type: FractalKind, octaves: i32) -> Self {
+        self.fractal_type = fractal_type;
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn build(&self) -> FastNoiseLite {
+        let mut noise = FastNoiseLite::with_seed(self.seed);
+        noise.set_noise_type(Some(match self.noise_type {
+            NoiseKind::OpenSimplex2 => NoiseType::OpenSimplex2,
+            NoiseKind::OpenSimplex2s => NoiseType::OpenSimplex2S,
+            NoiseKind::Cellular => NoiseType::Cellular,
+            NoiseKind::Perlin => NoiseType::Perlin,
+            NoiseKind::ValueCubic => NoiseType::ValueCubic,
+            NoiseKind::Value => NoiseType::Value,
+        }));
+        noise.set_frequency(Some(self.frequency));
+        noise.set_fractal_type(Some(match self.fractal_type {
+            FractalKind::None => FractalType::None,
+            FractalKind::Fbm => FractalType::FBm,
+            FractalKind::Ridged => FractalType::Ridged,
+            FractalKind::PingPong => FractalType::PingPong,
+        }));
+        noise.set_fractal_octaves(Some(self.octaves));
+        noise.set_fractal_ping_pong_strength(Some(self.ping_pong_strength));
+        noise
+    }
+}
@@ -0,0 +1,152 @@
+use std::f32::consts::TAU;
+use nalgebra_glm::Vec3;
+
+// Newton iterations for Kepler's equation, converges well below f32 precision even for comets
+const KEPLER_ITERATIONS: usize = 8;
+const KEPLER_TOLERANCE: f32 = 1e-6;
+
+/// Keplerian orbital elements. Angles are in radians and measured in the XZ plane,
+/// with +Y as the orbit's "north", so an orbit with zero inclination stays flat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orbit {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,       // 0 is a circle, values towards 1 give long comet-like ellipses
+    pub inclination: f32,        // Tilt of the orbital plane around the line of nodes
+    pub ascending_node: f32,     // Longitude of the ascending node, where the orbit crosses the plane going up
+    pub periapsis_argument: f32, // Angle from the ascending node to the closest approach
+    pub mean_anomaly: f32,       // Current position along the orbit, grows uniformly with time
+    pub mean_motion: f32,        // Radians of mean anomaly per second
+}
+
+impl Orbit {
+    pub fn advance(&mut self, delta_time: f32) {
+        // Keep the anomaly within [0, 2π] to prevent losing precision
+        self.mean_anomaly = (self.mean_anomaly + self.mean_motion * delta_time).rem_euclid(TAU);
+    }
+
+    /// Position relative to the focus (the orbit's center) at the current mean anomaly.
+    pub fn position(&self) -> Vec3 {
+        self.position_at_eccentric_anomaly(self.eccentric_anomaly())
+    }
+
+    /// Solves Kepler's equation `M = E - e sin(E)` for the eccentric anomaly `E`.
+    pub fn eccentric_anomaly(&self) -> f32 {
+        let e = self.eccentricity;
+        let m = self.mean_anomaly;
+
+        // Starting from π converges for every eccentricity, M is a better guess for round orbits
+        let mut anomaly = if e > 0.8 { std::f32::consts::PI } else { m };
+        for _ in 0..KEPLER_ITERATIONS {
+            let step = (anomaly - e * anomaly.sin() - m) / (1.0 - e * anomaly.cos());
+            anomaly -= step;
+            if step.abs() < KEPLER_TOLERANCE {
+                break;
+            }
+        }
+        anomaly
+    }
+
+    /// Closed polyline following the ellipse, relative to the focus.
+    pub fn path(&self, segments: usize) -> Vec<Vec3> {
+        // Sampling the eccentric anomaly evenly keeps the ends of long ellipses smooth
+        (0..segments)
+            .map(|i| self.position_at_eccentric_anomaly(TAU * i as f32 / segments as f32))
+            .collect()
+    }
+
+    fn position_at_eccentric_anomaly(&self, anomaly: f32) -> Vec3 {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis;
+
+        // Position in the orbital plane, periapsis along the first axis
+        let p = a * (anomaly.cos() - e);
+        let q = a * (1.0 - e * e).sqrt() * anomaly.sin();
+
+        self.to_world(p, q)
+    }
+
+    // Rotates the orbital plane by ω, i and Ω, mapping the reference plane onto XZ
+    fn to_world(self, p: f32, q: f32) -> Vec3 {
+        let (sin_w, cos_w) = self.periapsis_argument.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (sin_o, cos_o) = self.ascending_node.sin_cos();
+
+        let x = p * cos_w - q * sin_w;
+        let y = p * sin_w + q * cos_w;
+
+        let along_node = x;
+        let across_node = y * cos_i;
+        let height = y * sin_i;
+
+        Vec3::new(
+            along_node * cos_o - across_node * sin_o,
+            height,
+            along_node * sin_o + across_node * cos_o,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orbit(eccentricity: f32, mean_anomaly: f32) -> Orbit {
+        Orbit {
+            semi_major_axis: 10.0,
+            eccentricity,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            periapsis_argument: 0.0,
+            mean_anomaly,
+            mean_motion: 1.0,
+        }
+    }
+
+    fn kepler_residual(orbit: &Orbit) -> f32 {
+        let anomaly = orbit.eccentric_anomaly();
+        (anomaly - orbit.eccentricity * anomaly.sin() - orbit.mean_anomaly).abs()
+    }
+
+    #[test]
+    fn circular_orbit_keeps_mean_anomaly_and_radius() {
+        for i in 0..16 {
+            let orbit = orbit(0.0, TAU * i as f32 / 16.0);
+            assert!((orbit.eccentric_anomaly() - orbit.mean_anomaly).abs() < 1e-6);
+            assert!((orbit.position().norm() - 10.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn kepler_converges_for_eccentric_orbits() {
+        for eccentricity in [0.3, 0.7, 0.9, 0.99] {
+            for i in 0..64 {
+                let orbit = orbit(eccentricity, TAU * i as f32 / 64.0);
+                assert!(kepler_residual(&orbit) < 1e-4, "e = {}, M = {}", eccentricity, orbit.mean_anomaly);
+            }
+        }
+    }
+
+    #[test]
+    fn eccentric_orbit_reaches_periapsis_and_apoapsis() {
+        let periapsis = orbit(0.9, 0.0).position();
+        let apoapsis = orbit(0.9, std::f32::consts::PI).position();
+        assert!((periapsis.norm() - 1.0).abs() < 1e-3);
+        assert!((apoapsis.norm() - 19.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flat_orbit_stays_in_the_xz_plane() {
+        for i in 0..8 {
+            assert!(orbit(0.5, TAU * i as f32 / 8.0).position().y.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn advance_wraps_the_mean_anomaly() {
+        let mut orbit = orbit(0.2, 6.0);
+        orbit.advance(1.0);
+        assert!((0.0..TAU).contains(&orbit.mean_anomaly));
+        orbit.advance(-10.0);
+        assert!((0.0..TAU).contains(&orbit.mean_anomaly));
+    }
+}
@@ -0,0 +1,91 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use super::model::Model;
+use super::shadow::Occluder;
+
+/// A half-line in world space, `direction` is normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// The ray through pixel (`x`, `y`) of a `width` x `height` screen, from the near plane outwards.
+    /// Unprojects both ends through the inverse of `perspective_matrix * view_matrix`.
+    pub fn from_screen(x: f32, y: f32, width: f32, height: f32, perspective_matrix: &Mat4, view_matrix: &Mat4) -> Option<Ray> {
+        let inverse = (perspective_matrix * view_matrix).try_inverse()?;
+
+        // Screen y grows downwards, NDC y grows upwards
+        let ndc_x = 2.0 * x / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / height;
+
+        let unproject = |ndc_z: f32| {
+            let point = inverse * Vec4::new(ndc_x, ndc_y, ndc_z, 1.0);
+            point.xyz() / point.w
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        Some(Ray { origin: near, direction: (far - near).normalize() })
+    }
+
+    /// Both distances along the ray where it crosses a sphere, the nearer one first.
+    /// They can be negative when the sphere lies behind the origin or holds it.
+    pub fn sphere_crossings(&self, center: &Vec3, radius: f32) -> Option<(f32, f32)> {
+        let offset = self.origin - center;
+        let b = offset.dot(&self.direction);
+        let c = offset.norm_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        Some((-b - root, -b + root))
+    }
+
+    /// Distance along the ray to the first hit with a sphere, `None` if it misses or lies behind.
+    pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<f32> {
+        let (near, far) = self.sphere_crossings(&center, radius)?;
+        // Starting inside the sphere still counts, the hit is where the ray leaves it
+        [near, far].into_iter().find(|&distance| distance >= 0.0)
+    }
+
+    /// Distance along the ray to where it crosses a flat annulus around `center` facing `normal`,
+    /// `None` if it passes through the hole, outside the rim or behind the origin.
+    pub fn intersect_ring(&self, center: Vec3, normal: Vec3, inner_radius: f32, outer_radius: f32) -> Option<f32> {
+        let facing = self.direction.dot(&normal);
+        if facing.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = (center - self.origin).dot(&normal) / facing;
+        if distance < 0.0 {
+            return None;
+        }
+        let radial = (self.origin + self.direction * distance - center).norm();
+        (inner_radius..=outer_radius).contains(&radial).then_some(distance)
+    }
+}
+
+/// Radius of a sphere around the model's position that holds its whole mesh.
+pub fn bounding_radius(model: &dyn Model) -> f32 {
+    (model.get_bounds().radius * model.get_scale()).max(model.get_colision_radius())
+}
+
+/// Index of the nearest model the ray hits. Flat meshes are tested against their ring, so
+/// the rings around a planet don't swallow clicks meant for the planet inside them.
+pub fn pick(ray: &Ray, models: &[Box<dyn Model>]) -> Option<usize> {
+    models.iter()
+        .enumerate()
+        .filter_map(|(index, model)| {
+            let distance = match Occluder::from_model(model.as_ref()) {
+                Occluder::Sphere { center, radius } => ray.intersect_sphere(center, radius),
+                Occluder::Ring { center, normal, inner_radius, outer_radius, .. } => {
+                    ray.intersect_ring(center, normal, inner_radius, outer_radius)
+                }
+            };
+            distance.map(|distance| (index, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
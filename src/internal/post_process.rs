@@ -0,0 +1,236 @@
+use nalgebra_glm::{Vec2, Vec3};
+use rayon::prelude::*;
+
+use super::framebuffer::Framebuffer;
+
+// Bloom is blurred at half resolution, it is soft anyway and four times cheaper
+const BLOOM_DOWNSAMPLE: usize = 2;
+// Share of the grain that is added on top instead of scaled, so it also shows on black space
+const GRAIN_FLOOR: f32 = 0.02;
+
+/// One image effect applied to the finished HDR frame, before tone mapping.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostEffect {
+    /// Light brighter than `threshold` bleeds into its surroundings, like the sun's glow.
+    Bloom {
+        threshold: f32, // Luminance above which pixels glow
+        intensity: f32, // How much of the glow is added back
+        radius: f32,    // Gaussian sigma of the glow in pixels
+    },
+    /// Darkens the corners of the frame.
+    Vignette {
+        strength: f32, // Darkening in the corners, 1 turns them black
+        radius: f32,   // Where the darkening starts, 0 at the center and 1 in the corners
+        softness: f32, // How far it takes to reach full strength
+    },
+    /// Flickering noise, different every frame.
+    Grain {
+        strength: f32,
+    },
+    /// Red and blue drift apart towards the edges, like a cheap lens.
+    ChromaticAberration {
+        strength: f32, // Offset of the red and blue channels as a share of the distance to the center
+    },
+}
+
+/// A post-processing effect that can be switched off without losing its settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostPass {
+    pub effect: PostEffect,
+    pub enabled: bool,
+}
+
+/// The passes a scene runs on every frame, in order.
+#[derive(Clone, Debug, Default)]
+pub struct PostProcessing {
+    pub passes: Vec<PostPass>,
+}
+
+impl PostProcessing {
+    pub fn new(passes: Vec<PostPass>) -> Self {
+        PostProcessing { passes }
+    }
+
+    /// Runs the enabled passes over the framebuffer's HDR target. `seed` changes the grain,
+    /// the same seed gives the same frame.
+    pub fn apply(&self, framebuffer: &mut Framebuffer, seed: u32) {
+        for pass in self.passes.iter().filter(|pass| pass.enabled) {
+            pass.effect.apply(framebuffer, seed);
+        }
+    }
+
+    /// Switches the pass at `index` on or off, returns false if there is no such pass.
+    pub fn toggle(&mut self, index: usize) -> bool {
+        match self.passes.get_mut(index) {
+            Some(pass) => {
+                pass.enabled = !pass.enabled;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl PostEffect {
+    pub fn apply(&self, framebuffer: &mut Framebuffer, seed: u32) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let image = &mut framebuffer.hdr_buffer;
+
+        match *self {
+            PostEffect::Bloom { threshold, intensity, radius } => bloom(image, width, height, threshold, intensity, radius),
+            PostEffect::Vignette { strength, radius, softness } => {
+                // Distances are measured in half diagonals, so the corners are at 1
+                let center = Vec2::new(width as f32, height as f32) * 0.5;
+                let half_diagonal = center.norm();
+                image.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let distance = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center).norm() / half_diagonal;
+                        *pixel *= 1.0 - strength * smoothstep(radius, radius + softness, distance);
+                    }
+                });
+            }
+            PostEffect::Grain { strength } => {
+                image.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let noise = hash_noise(x as u32, y as u32, seed) * strength;
+                        *pixel = (*pixel * (1.0 + noise)).add_scalar(noise * GRAIN_FLOOR).map(|channel| channel.max(0.0));
+                    }
+                });
+            }
+            PostEffect::ChromaticAberration { strength } => {
+                let source = image.clone();
+                let center = Vec2::new(width as f32, height as f32) * 0.5;
+                image.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let position = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                        let offset = (position - center) * strength;
+                        pixel.x = sample(&source, width, height, &(position + offset)).x;
+                        pixel.z = sample(&source, width, height, &(position - offset)).z;
+                    }
+                });
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom { .. } => "bloom",
+            PostEffect::Vignette { .. } => "vignette",
+            PostEffect::Grain { .. } => "grain",
+            PostEffect::ChromaticAberration { .. } => "chromatic aberration",
+        }
+    }
+}
+
+/// Bright pass at a lower resolution, separable Gaussian blur, then added back over the frame.
+fn bloom(image: &mut [Vec3], width: usize, height: usize, threshold: f32, intensity: f32, radius: f32) {
+    let small_width = width.div_ceil(BLOOM_DOWNSAMPLE);
+    let small_height = height.div_ceil(BLOOM_DOWNSAMPLE);
+
+    // Bright pass: only the light above the threshold glows, keeping its hue
+    let source = &*image;
+    let bright: Vec<Vec3> = (0..small_width * small_height)
+        .into_par_iter()
+        .map(|index| {
+            let (small_x, small_y) = (index % small_width, index / small_width);
+            let mut sum = Vec3::zeros();
+            let mut count = 0.0;
+            for y in small_y * BLOOM_DOWNSAMPLE..((small_y + 1) * BLOOM_DOWNSAMPLE).min(height) {
+                for x in small_x * BLOOM_DOWNSAMPLE..((small_x + 1) * BLOOM_DOWNSAMPLE).min(width) {
+                    let light = source[y * width + x];
+                    let luminance = luminance(&light);
+                    if luminance > threshold {
+                        sum += light * ((luminance - threshold) / luminance);
+                    }
+                    count += 1.0;
+                }
+            }
+            sum / count
+        })
+        .collect();
+
+    let kernel = gaussian_kernel(radius / BLOOM_DOWNSAMPLE as f32);
+    let blurred = blur_vertical(&blur_horizontal(&bright, small_width, &kernel), small_width, small_height, &kernel);
+
+    image.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let position = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) / BLOOM_DOWNSAMPLE as f32;
+            *pixel += sample(&blurred, small_width, small_height, &position) * intensity;
+        }
+    });
+}
+
+// Normalized weights from the center tap outwards, cut off at three sigmas
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(0.5);
+    let taps = (sigma * 3.0).ceil() as usize;
+    let weights: Vec<f32> = (0..=taps).map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+fn blur_horizontal(image: &[Vec3], width: usize, kernel: &[f32]) -> Vec<Vec3> {
+    let mut output = vec![Vec3::zeros(); image.len()];
+    output.par_chunks_mut(width).zip(image.par_chunks(width)).for_each(|(output, row)| {
+        for (x, pixel) in output.iter_mut().enumerate() {
+            *pixel = row[x] * kernel[0];
+            for (i, weight) in kernel.iter().enumerate().skip(1) {
+                let left = x.saturating_sub(i);
+                let right = (x + i).min(width - 1);
+                *pixel += (row[left] + row[right]) * *weight;
+            }
+        }
+    });
+    output
+}
+
+fn blur_vertical(image: &[Vec3], width: usize, height: usize, kernel: &[f32]) -> Vec<Vec3> {
+    let mut output = vec![Vec3::zeros(); image.len()];
+    output.par_chunks_mut(width).enumerate().for_each(|(y, output)| {
+        for (x, pixel) in output.iter_mut().enumerate() {
+            *pixel = image[y * width + x] * kernel[0];
+            for (i, weight) in kernel.iter().enumerate().skip(1) {
+                let up = y.saturating_sub(i);
+                let down = (y + i).min(height - 1);
+                *pixel += (image[up * width + x] + image[down * width + x]) * *weight;
+            }
+        }
+    });
+    output
+}
+
+// Bilinear lookup at `position` in pixels, pixel centers are at +0.5 and the edges are clamped
+fn sample(image: &[Vec3], width: usize, height: usize, position: &Vec2) -> Vec3 {
+    let x = (position.x - 0.5).clamp(0.0, (width - 1) as f32);
+    let y = (position.y - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let top = image[y0 * width + x0].lerp(&image[y0 * width + x1], tx);
+    let bottom = image[y1 * width + x0].lerp(&image[y1 * width + x1], tx);
+    top.lerp(&bottom, ty)
+}
+
+fn luminance(light: &Vec3) -> f32 {
+    light.dot(&Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 <= edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Integer hash of the pixel and the frame, in [-1, 1]
+fn hash_noise(x: u32, y: u32, seed: u32) -> f32 {
+    let mut hash = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841) ^ seed.wrapping_mul(0xcb1a_b31f);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7feb_352d);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846c_a68b);
+    hash ^= hash >> 16;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
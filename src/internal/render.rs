@@ -1,93 +1,269 @@
-use nalgebra_glm::{look_at, perspective, Mat4, Vec2, Vec3, Vec4};
+use nalgebra_glm::{look_at, perspective, Mat4, Vec3, Vec4};
+use rayon::prelude::*;
 use std::f32::consts::PI;
 use std::sync::Arc;
 
+use super::atmosphere::Atmosphere;
 use super::camera::Camera;
+use super::clipping::{clip_line, clip_triangle, project_to_screen};
 use super::entity::vertex::Vertex;
 use super::framebuffer::Framebuffer;
-use super::shader::vertex_shader;
+use super::shader::{vertex_shader, FragmentShader};
 use super::line::{line, triangle_flat_shade};
-use super::entity::fragment::Fragment;
+use super::material::{BlendMode, Material};
 use super::entity::color::Color;
+use super::entity::fragment::Fragment;
+use super::entity::light::Light;
+use super::shadow::{light_visibility, Occluder};
+use super::time_loop::LoopState;
+use super::tone_mapping::ToneMapping;
 
-pub struct Uniforms {
+pub struct Uniforms<'a> {
     pub model_matrix: Mat4,
     pub view_matrix: Mat4,
     pub perspective_matrix: Mat4,
     pub viewport_matrix: Mat4,
-    pub time: f32
+    pub time: f32,
+    pub camera_position: Vec3,
+    pub lights: &'a [Light],
+    pub occluders: &'a [Occluder], // Bodies that can shadow each other, see `light_visibility`
+    pub time_loop: LoopState,
 }
 
+/// How `render` turns assembled triangles into pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RasterMode {
+    /// Rasterize and shade every triangle on the calling thread, in submission order.
+    SingleThreaded,
+    /// Bin triangles into bands of `TILE_HEIGHT` rows and rasterize the bands in parallel.
+    /// Each band keeps submission order, so the output matches `SingleThreaded`.
+    Tiled,
+}
+
+/// Settings shared by the interactive and headless entry points.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub raster_mode: RasterMode,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32, // Multiplies the HDR frame before tone mapping
+    pub post_processing: bool, // Runs the scene's post-processing passes, off skips all of them
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            raster_mode: RasterMode::Tiled,
+            tone_mapping: ToneMapping::default(),
+            exposure: 1.0,
+            post_processing: true,
+        }
+    }
+}
+
+const TILE_HEIGHT: usize = 16;
+
+// Material constants shared by every model
+const AMBIENT_INTENSITY: f32 = 0.05;
+const SPECULAR_STRENGTH: f32 = 0.3;
+const SHININESS: f32 = 32.0;
+
+// Atmospheres glow over what is behind them and hide nothing drawn after them
+const ATMOSPHERE_MATERIAL: Material = Material { blend: BlendMode::Additive, depth_write: false };
+
+// Supernova shockwave look
+const SHOCKWAVE_WIDTH: f32 = 2.0;
+const SHOCKWAVE_COLOR: Color = Color { r: 200, g: 230, b: 255, a: 255 };
+const SCORCHED_COLOR: Color = Color { r: 60, g: 15, b: 5, a: 255 };
+const SHOCKWAVE_BRIGHTNESS: f32 = 3.0;
+
 pub fn render(framebuffer: &mut Framebuffer, 
     uniforms: &Uniforms,
     vertex_array: Arc<Vec<Vertex>>,
-    camera: &Camera, 
-    shader: fn(&Fragment, &Uniforms) -> Color) {
-    // Vertex Shader Stage
+    shader: &dyn FragmentShader,
+    material: &Material,
+    raster_mode: RasterMode) {
+    let triangles = transform(uniforms, &vertex_array);
+
+    // Rasterization and Fragment Processing Stages
+    let shade = |fragment: &Fragment| shade_fragment(fragment, uniforms, shader);
+    rasterize(framebuffer, &triangles, material, &shade, raster_mode);
+}
+
+/// Draws the atmosphere shell of the planet at `uniforms.model_matrix`, added on top of
+/// whatever is behind it. It belongs in the transparent pass, after the opaque models.
+pub fn render_atmosphere(framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: Arc<Vec<Vertex>>,
+    atmosphere: &Atmosphere,
+    raster_mode: RasterMode) {
+    let triangles = transform(uniforms, &vertex_array);
+
+    let center = (uniforms.model_matrix * Vec3::zeros().push(1.0)).xyz();
+    let shade = |fragment: &Fragment| {
+        atmosphere.scatter(&center, &uniforms.camera_position, &fragment.world_position, uniforms.lights).push(1.0)
+    };
+    rasterize(framebuffer, &triangles, &ATMOSPHERE_MATERIAL, &shade, raster_mode);
+}
+
+/// Vertex Shader and Primitive Assembly Stages: the model's triangles, clipped and on screen.
+fn transform(uniforms: &Uniforms, vertex_array: &[Vertex]) -> Vec<[Vertex; 3]> {
     let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
     let tranformation_matrix = uniforms.perspective_matrix * uniforms.view_matrix * uniforms.model_matrix;
     for vertex in vertex_array.iter() {
-        let transformed = vertex_shader(vertex, &tranformation_matrix, &uniforms);
+        let transformed = vertex_shader(vertex, &tranformation_matrix, uniforms);
         transformed_vertices.push(transformed);
     }
-    // println!("{}", uniforms.model_matrix);
-    
-    // println!("a: {}, b:{}", vertex_array[1].position, transformed_vertices[1].position);
 
-    // Primitive Assembly Stage
-    let triangles = assembly(&transformed_vertices, true);
+    assembly(&transformed_vertices, &uniforms.viewport_matrix)
+}
 
-    // Rasterization Stage
-    let mut fragments = Vec::new();
-    let camera_view_dir = (camera.center - camera.eye).normalize();
-    for tri in &triangles {
-        fragments.extend(triangle_flat_shade(&tri[0], &tri[1], &tri[2], camera_view_dir));
+fn rasterize(
+    framebuffer: &mut Framebuffer,
+    triangles: &[[Vertex; 3]],
+    material: &Material,
+    shade: &(dyn Fn(&Fragment) -> Vec4 + Sync),
+    raster_mode: RasterMode,
+) {
+    match raster_mode {
+        RasterMode::SingleThreaded => rasterize_serial(framebuffer, triangles, material, shade),
+        RasterMode::Tiled => rasterize_tiled(framebuffer, triangles, material, shade),
     }
+}
 
-    // Fragment Processing Stage
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let color = shader(&fragment, uniforms);
-            framebuffer.set_current_color(color);
-            framebuffer.draw_point(x, y, fragment.depth);
+fn rasterize_serial(
+    framebuffer: &mut Framebuffer,
+    triangles: &[[Vertex; 3]],
+    material: &Material,
+    shade: &(dyn Fn(&Fragment) -> Vec4 + Sync),
+) {
+    let bounds = (0, 0, framebuffer.width as i32 - 1, framebuffer.height as i32 - 1);
+
+    for tri in triangles {
+        triangle_flat_shade(&tri[0], &tri[1], &tri[2], bounds, |fragment| {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            let color = shade(&fragment);
+            framebuffer.blend_point(x, y, fragment.depth, &color, material);
+        });
+    }
+}
+
+fn rasterize_tiled(
+    framebuffer: &mut Framebuffer,
+    triangles: &[[Vertex; 3]],
+    material: &Material,
+    shade: &(dyn Fn(&Fragment) -> Vec4 + Sync),
+) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+
+    // Binning: every tile gets the indices of the triangles overlapping its rows, in submission order
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); height.div_ceil(TILE_HEIGHT)];
+    for (index, tri) in triangles.iter().enumerate() {
+        let min_y = tri.iter().map(|v| v.transformed_position.y).fold(f32::INFINITY, f32::min).floor();
+        let max_y = tri.iter().map(|v| v.transformed_position.y).fold(f32::NEG_INFINITY, f32::max).ceil();
+
+        // NaN bounds (degenerate projections) fail both comparisons and are skipped as well
+        if !(max_y >= 0.0 && min_y < height as f32) {
+            continue;
+        }
+
+        let first_tile = min_y.max(0.0) as usize / TILE_HEIGHT;
+        let last_tile = (max_y as usize).min(height - 1) / TILE_HEIGHT;
+        for bin in &mut bins[first_tile..=last_tile] {
+            bin.push(index);
+        }
+    }
+
+    framebuffer
+        .par_tiles_mut(TILE_HEIGHT)
+        .zip(bins.par_iter())
+        .for_each(|(mut tile, bin)| {
+            let bounds = (0, tile.y_start as i32, width as i32 - 1, tile.y_end as i32 - 1);
+
+            for &index in bin {
+                let tri = &triangles[index];
+                triangle_flat_shade(&tri[0], &tri[1], &tri[2], bounds, |fragment| {
+                    let x = fragment.position.x as usize;
+                    let y = fragment.position.y as usize;
+                    let color = shade(&fragment);
+                    tile.blend_point(x, y, fragment.depth, &color, material);
+                });
+            }
+        });
+}
+
+/// Fragment Processing Stage: evaluates the scene lights for the fragment, dimmed by the
+/// bodies shadowing it, lights the albedo the model's shader returns with them and adds
+/// the specular highlight on top. Returns linear light and the shader's alpha.
+fn shade_fragment(fragment: &Fragment, uniforms: &Uniforms, shader: &dyn FragmentShader) -> Vec4 {
+    let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+
+    let mut diffuse = Vec3::repeat(AMBIENT_INTENSITY);
+    let mut specular = Vec3::zeros();
+    for light in uniforms.lights {
+        let (light_diffuse, light_specular) =
+            light.illuminate(&fragment.world_position, &fragment.normal, &view_dir, SHININESS);
+        if light_diffuse <= 0.0 {
+            continue;
         }
+        // Eclipses and ring shadows, only worth testing on the lit side
+        let visibility = light_visibility(uniforms.occluders, &fragment.world_position, light);
+        let color = light.color.to_linear();
+        diffuse += color * (light_diffuse * visibility);
+        specular += color * (light_specular * SPECULAR_STRENGTH * visibility);
     }
+
+    // Lighting happens in linear space, highlights stay unclamped so they can bloom past white before tone mapping
+    let albedo = shader.shade(fragment, uniforms);
+    let albedo_linear = albedo.to_linear();
+    let light = albedo_linear.component_mul(&diffuse) + albedo_linear * shader.emission() + specular;
+    shockwave(light, fragment, &uniforms.time_loop).push(albedo.alpha())
 }
 
-fn assembly(vertices: &[Vertex], should_optimize: bool) -> Vec<&[Vertex]> {
-    let triangles = vertices.chunks(3);
-
-    if should_optimize {
-        triangles
-            .filter(|triangle_vertices| {
-                let range = -1.0..1.0;
-                let a = &triangle_vertices[0];
-                let b = &triangle_vertices[1];
-                let c = &triangle_vertices[2];
-                let a_in_range = range.contains(&a.frustrum_position.x)
-                    && range.contains(&a.frustrum_position.y)
-                    && range.contains(&a.frustrum_position.z);
-                let b_in_range = range.contains(&b.frustrum_position.x)
-                    && range.contains(&b.frustrum_position.y)
-                    && range.contains(&b.frustrum_position.z);
-                let c_in_range = range.contains(&c.frustrum_position.x)
-                    && range.contains(&c.frustrum_position.y)
-                    && range.contains(&c.frustrum_position.z);
-
-                a_in_range || b_in_range || c_in_range
-            })
-            .collect()
+/// Lights up the supernova shockwave front where it crosses a surface and scorches what it already passed.
+fn shockwave(light: Vec3, fragment: &Fragment, state: &LoopState) -> Vec3 {
+    if state.shockwave_radius <= 0.0 {
+        return light;
+    }
+
+    let distance = (fragment.world_position - state.shockwave_center).norm();
+    let light = if distance < state.shockwave_radius {
+        light.lerp(&SCORCHED_COLOR.to_linear(), 0.6)
     } else {
-        triangles.collect()
+        light
+    };
+
+    let front = 1.0 - (distance - state.shockwave_radius).abs() / SHOCKWAVE_WIDTH;
+    if front > 0.0 {
+        light.lerp(&(SHOCKWAVE_COLOR.to_linear() * SHOCKWAVE_BRIGHTNESS), front)
+    } else {
+        light
+    }
+}
+
+/// Groups vertices into triangles, clips them against the view frustum in homogeneous
+/// clip space and only then projects the surviving vertices to the screen.
+fn assembly(vertices: &[Vertex], viewport_matrix: &Mat4) -> Vec<[Vertex; 3]> {
+    let mut triangles = Vec::with_capacity(vertices.len() / 3);
+
+    for triangle in vertices.chunks_exact(3) {
+        for mut clipped in clip_triangle(&triangle[0], &triangle[1], &triangle[2]) {
+            for vertex in clipped.iter_mut() {
+                project_to_screen(vertex, viewport_matrix);
+            }
+            triangles.push(clipped);
+        }
     }
+
+    triangles
 }
 
 pub fn draw_orbit(
     framebuffer: &mut Framebuffer,
     uniforms: &Uniforms,
     segments: &[Vertex],
+    orbit_frame: &Mat4,
     camera: &Camera,
     orbit_color: Color,
 ) {
@@ -95,11 +271,15 @@ pub fn draw_orbit(
     let mut transformed_vertices = Vec::with_capacity(segments.len());
 
     let modified_uniforms = &Uniforms { 
-        model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, Vec3::new(0.0, 0.0, 0.0)),
+        model_matrix: *orbit_frame,
         view_matrix: uniforms.view_matrix,
         perspective_matrix: uniforms.perspective_matrix, 
         viewport_matrix: uniforms.viewport_matrix,
-        time: 0.0 };
+        time: 0.0,
+        camera_position: uniforms.camera_position,
+        lights: &[],
+        occluders: &[],
+        time_loop: uniforms.time_loop };
 
     let transformation_matrix = modified_uniforms.perspective_matrix * modified_uniforms.view_matrix * modified_uniforms.model_matrix;
 
@@ -115,17 +295,11 @@ pub fn draw_orbit(
         let start = &transformed_vertices[i];
         let end = &transformed_vertices[(i + 1) % segments.len()]; // Wrap around for closed orbit
 
-        // Only keep lines where at least one endpoint is within the clip space range
-        let range = -1.0..1.0;
-        let start_in_range = range.contains(&start.frustrum_position.x)
-            && range.contains(&start.frustrum_position.y)
-            && range.contains(&start.frustrum_position.z);
-        let end_in_range = range.contains(&end.frustrum_position.x)
-            && range.contains(&end.frustrum_position.y)
-            && range.contains(&end.frustrum_position.z);
-
-        if start_in_range || end_in_range {
-            lines.push((start.clone(), end.clone()));
+        // Only keep the part of the segment inside the view frustum
+        if let Some((mut start, mut end)) = clip_line(start, end) {
+            project_to_screen(&mut start, &modified_uniforms.viewport_matrix);
+            project_to_screen(&mut end, &modified_uniforms.viewport_matrix);
+            lines.push((start, end));
         }
     }
 
@@ -189,8 +363,9 @@ pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
     look_at(&eye, &center, &up)
 }
 
-pub fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
+/// `fov` is the vertical field of view in degrees.
+pub fn create_perspective_matrix(window_width: f32, window_height: f32, fov: f32) -> Mat4 {
+    let fov = fov * PI / 180.0;
     let aspect_ratio = window_width / window_height;
     let near = 0.5;
     let far = 150.0;
@@ -0,0 +1,1060 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use nalgebra_glm::{quat_identity, Mat4, Quat, Vec3};
+use serde::Deserialize;
+
+use super::atmosphere::Atmosphere;
+use super::camera::{Camera, CameraMode};
+use super::collision::{detect_collisions, resolve_ship_collisions, CollisionEvent};
+use super::entity::color::Color;
+use super::entity::light::Light;
+use super::entity::skybox::Skybox;
+use super::entity::vertex::Vertex;
+use super::gravity::{GravityBody, GravitySimulation};
+use super::material::{BlendMode, Material};
+use super::model::{Model, Planet};
+use super::object::Obj;
+use super::orbit::Orbit;
+use super::picking::bounding_radius;
+use super::post_process::{PostEffect, PostPass, PostProcessing};
+use super::shader::FragmentShader;
+use super::shadow::Occluder;
+use super::time_loop::{LoopState, TimeLoop};
+use super::ship::{Ship, ShipControls};
+use super::shader_registry::{ShaderError, ShaderParams, ShaderRegistry};
+
+/// Everything a frame needs, shared by the interactive window and the headless renderer.
+pub struct Scene {
+    pub camera: Camera,
+    pub skybox: Skybox,
+    pub models: Vec<Box<dyn Model>>, // models[0] is always the player controlled ship
+    pub lights: Vec<Light>,
+    pub gravity: Option<GravitySimulation>, // Set when the scene file has a [gravity] table
+    pub time_loop: Option<TimeLoop>,        // Set when the scene file has a [time_loop] table
+    pub post_processing: PostProcessing,    // Passes run on every finished frame, in file order
+    initial: InitialState,
+}
+
+/// Everything `Scene::reset` puts back when the time loop starts over.
+struct InitialState {
+    camera: Camera,
+    ship_position: Vec3,
+    ship_orientation: Quat,
+    planets: Vec<Option<(Orbit, Vec3)>>, // Orbit and rotation for every model that is a planet
+    gravity: Option<GravitySimulation>,
+}
+
+impl InitialState {
+    fn capture(camera: &Camera, models: &[Box<dyn Model>], gravity: &Option<GravitySimulation>) -> Self {
+        let ship = models[0].as_any().downcast_ref::<Ship>();
+        InitialState {
+            camera: camera.clone(),
+            ship_position: models[0].get_position(),
+            ship_orientation: ship.map_or_else(quat_identity, |ship| ship.orientation),
+            planets: models
+                .iter()
+                .map(|model| model.as_any().downcast_ref::<Planet>().map(|planet| (planet.orbit, planet.rotation)))
+                .collect(),
+            gravity: gravity.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, source: toml::de::Error },
+    Shader { body: String, source: ShaderError },
+    MissingMesh { body: String, path: String },
+    InvalidMesh { path: String, source: tobj::LoadError },
+    InvalidValue { body: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "could not read scene file '{}': {}", path, source),
+            SceneError::Parse { path, source } => write!(f, "could not parse scene file '{}': {}", path, source),
+            SceneError::Shader { body, source } => write!(f, "'{}' has a bad shader: {}", body, source),
+            SceneError::MissingMesh { body, path } => write!(f, "'{}' references missing mesh file '{}'", body, path),
+            SceneError::InvalidMesh { path, source } => write!(f, "could not load mesh '{}': {}", path, source),
+            SceneError::InvalidValue { body, message } => write!(f, "'{}' is invalid: {}", body, message),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+// ----- File format -----
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    pub skybox: SkyboxDescription,
+    pub ship: ShipDescription,
+    #[serde(default)]
+    pub planets: Vec<PlanetDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    pub gravity: Option<GravityDescription>,
+    pub time_loop: Option<TimeLoopDescription>,
+    #[serde(default)]
+    pub post_processing: Vec<PostPassDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub eye: [f32; 3],
+    pub center: [f32; 3],
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+    pub min_radius: f32,
+    pub max_radius: f32,
+    pub bird_view_eye: [f32; 3],
+    pub bird_view_center: [f32; 3],
+    #[serde(default)]
+    pub mode: CameraMode,
+    pub focus: Option<String>, // Body the orbit camera follows, "ship" or a planet name, the ship by default
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyboxDescription {
+    pub stars: usize,
+    pub distance: f32,
+    pub stars_color: [u8; 3],
+    pub space_color: [u8; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipDescription {
+    pub mesh: String,
+    pub shader: String,
+    pub position: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    pub collision_radius: f32,
+    #[serde(default = "default_ship_mass")]
+    pub mass: f32,
+    #[serde(default = "default_ship_thrust")]
+    pub thrust: f32,
+    #[serde(default = "default_ship_turn_rate")]
+    pub turn_rate: f32,
+    #[serde(default = "default_ship_angular_damping")]
+    pub angular_damping: f32,
+    #[serde(default)]
+    pub gravity: bool,
+    #[serde(default = "default_ship_restitution")]
+    pub restitution: f32,
+    #[serde(default = "default_ship_friction")]
+    pub friction: f32,
+    #[serde(default)]
+    pub shader_params: toml::Table,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanetDescription {
+    pub name: String,
+    pub mesh: String,
+    pub shader: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(alias = "semi_major_axis")]
+    pub orbit_radius: f32,
+    #[serde(default, alias = "mean_anomaly")]
+    pub orbit_angle: f32,
+    #[serde(alias = "mean_motion")]
+    pub orbit_speed: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32,
+    #[serde(default)]
+    pub ascending_node: f32,
+    #[serde(default)]
+    pub periapsis_argument: f32,
+    pub collision_radius: f32,
+    #[serde(default)]
+    pub center: [f32; 3],
+    #[serde(default = "default_orbit_segments")]
+    pub orbit_segments: usize,
+    #[serde(default)]
+    pub axial_tilt: f32,
+    #[serde(default)]
+    pub spin_period: f32,
+    #[serde(default)]
+    pub mass: f32, // Only used with [gravity], a positive mass replaces the scripted orbit
+    #[serde(default)]
+    pub velocity: [f32; 3],
+    #[serde(default)]
+    pub parent: Option<String>, // Name of an earlier planet this body orbits and moves with
+    #[serde(default)]
+    pub shader_params: toml::Table,
+    #[serde(default)]
+    pub atmosphere: Option<AtmosphereDescription>,
+    #[serde(default)]
+    pub blend: BlendMode,
+    #[serde(default)]
+    pub depth_write: Option<bool>, // Defaults to writing depth only for opaque bodies
+}
+
+/// A scattering shell around a planet. Coefficients are per shell thickness, so the
+/// defaults look alike on atmospheres of any size.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AtmosphereDescription {
+    pub radius: f32, // Outer radius in world units, larger than the planet's scale
+    #[serde(default = "default_rayleigh")]
+    pub rayleigh: [f32; 3],
+    #[serde(default = "default_mie")]
+    pub mie: f32,
+    #[serde(default = "default_mie_direction")]
+    pub mie_direction: f32,
+    #[serde(default = "default_light_color")]
+    pub color: [u8; 3],
+    #[serde(default = "default_atmosphere_intensity")]
+    pub intensity: f32,
+}
+
+impl AtmosphereDescription {
+    fn build(&self, planet_radius: f32) -> Atmosphere {
+        let thickness = self.radius - planet_radius;
+        Atmosphere {
+            radius: self.radius,
+            planet_radius,
+            rayleigh: to_vec3(self.rayleigh) / thickness,
+            mie: self.mie / thickness,
+            mie_direction: self.mie_direction,
+            color: to_color(self.color),
+            intensity: self.intensity,
+        }
+    }
+}
+
+/// Turns on the N-body mode, planets with a `mass` are then moved by gravity.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GravityDescription {
+    #[serde(default = "default_gravity_constant")]
+    pub constant: f32,
+    #[serde(default = "default_gravity_substep")]
+    pub substep: f32,
+    #[serde(default = "default_gravity_softening")]
+    pub softening: f32,
+}
+
+/// Turns on the time loop: the sun ages, goes supernova and the scene resets.
+/// Times are seconds since the loop started.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeLoopDescription {
+    pub sun: String, // Name of the planet that plays the sun
+    #[serde(default = "default_loop_length")]
+    pub length: f32,
+    #[serde(default = "default_expansion_start")]
+    pub expansion_start: f32,
+    #[serde(default = "default_collapse_start")]
+    pub collapse_start: f32,
+    #[serde(default = "default_supernova_start")]
+    pub supernova_start: f32,
+    #[serde(default = "default_giant_scale")]
+    pub giant_scale: f32,
+    #[serde(default = "default_collapsed_scale")]
+    pub collapsed_scale: f32,
+    #[serde(default = "default_shockwave_speed")]
+    pub shockwave_speed: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Point {
+        position: [f32; 3],
+        #[serde(default)]
+        radius: f32, // Size of the light source, 0 casts hard shadows
+        #[serde(default = "default_light_color")]
+        color: [u8; 3],
+        #[serde(default = "default_light_intensity")]
+        intensity: f32,
+    },
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [u8; 3],
+        #[serde(default = "default_light_intensity")]
+        intensity: f32,
+    },
+}
+
+/// One `[[post_processing]]` pass, run on the frame in the order they are listed.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PostPassDescription {
+    Bloom {
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default = "default_bloom_threshold")]
+        threshold: f32,
+        #[serde(default = "default_bloom_intensity")]
+        intensity: f32,
+        #[serde(default = "default_bloom_radius")]
+        radius: f32,
+    },
+    Vignette {
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default = "default_vignette_strength")]
+        strength: f32,
+        #[serde(default = "default_vignette_radius")]
+        radius: f32,
+        #[serde(default = "default_vignette_softness")]
+        softness: f32,
+    },
+    Grain {
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default = "default_grain_strength")]
+        strength: f32,
+    },
+    ChromaticAberration {
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default = "default_chromatic_aberration_strength")]
+        strength: f32,
+    },
+}
+
+impl PostPassDescription {
+    fn build(&self) -> PostPass {
+        match *self {
+            PostPassDescription::Bloom { enabled, threshold, intensity, radius } => PostPass {
+                effect: PostEffect::Bloom { threshold, intensity, radius },
+                enabled,
+            },
+            PostPassDescription::Vignette { enabled, strength, radius, softness } => PostPass {
+                effect: PostEffect::Vignette { strength, radius, softness },
+                enabled,
+            },
+            PostPassDescription::Grain { enabled, strength } => PostPass {
+                effect: PostEffect::Grain { strength },
+                enabled,
+            },
+            PostPassDescription::ChromaticAberration { enabled, strength } => PostPass {
+                effect: PostEffect::ChromaticAberration { strength },
+                enabled,
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            PostPassDescription::Bloom { threshold, intensity, radius, .. } => {
+                if threshold < 0.0 || intensity < 0.0 || radius <= 0.0 {
+                    return Err("bloom threshold and intensity must not be negative, radius must be positive".to_string());
+                }
+            }
+            PostPassDescription::Vignette { strength, radius, softness, .. } => {
+                if !(0.0..=1.0).contains(&strength) || radius < 0.0 || softness < 0.0 {
+                    return Err("vignette strength must be in [0, 1], radius and softness must not be negative".to_string());
+                }
+            }
+            PostPassDescription::Grain { strength, .. } | PostPassDescription::ChromaticAberration { strength, .. } => {
+                if strength < 0.0 {
+                    return Err(format!("strength must not be negative, got {}", strength));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LightDescription {
+    fn build(&self) -> Light {
+        match self {
+            LightDescription::Point { position, radius, color, intensity } => {
+                Light::point(to_vec3(*position), *radius, to_color(*color), *intensity)
+            }
+            LightDescription::Directional { direction, color, intensity } => {
+                Light::directional(to_vec3(*direction), to_color(*color), *intensity)
+            }
+        }
+    }
+}
+
+fn default_light_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+fn default_light_intensity() -> f32 {
+    1.0
+}
+
+// Blue scatters about five times more than red, like air
+fn default_rayleigh() -> [f32; 3] {
+    [0.35, 0.8, 2.0]
+}
+
+fn default_mie() -> f32 {
+    0.4
+}
+
+fn default_mie_direction() -> f32 {
+    0.76
+}
+
+fn default_atmosphere_intensity() -> f32 {
+    6.0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_bloom_threshold() -> f32 {
+    1.0
+}
+
+fn default_bloom_intensity() -> f32 {
+    0.6
+}
+
+fn default_bloom_radius() -> f32 {
+    12.0
+}
+
+fn default_vignette_strength() -> f32 {
+    0.4
+}
+
+fn default_vignette_radius() -> f32 {
+    0.5
+}
+
+fn default_vignette_softness() -> f32 {
+    0.5
+}
+
+fn default_grain_strength() -> f32 {
+    0.05
+}
+
+fn default_chromatic_aberration_strength() -> f32 {
+    0.002
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_ship_mass() -> f32 {
+    1.0
+}
+
+fn default_ship_thrust() -> f32 {
+    20.0
+}
+
+fn default_ship_turn_rate() -> f32 {
+    3.0
+}
+
+fn default_ship_angular_damping() -> f32 {
+    2.0
+}
+
+fn default_ship_restitution() -> f32 {
+    0.4
+}
+
+fn default_ship_friction() -> f32 {
+    3.0
+}
+
+fn default_gravity_constant() -> f32 {
+    1.0
+}
+
+fn default_gravity_substep() -> f32 {
+    0.01
+}
+
+fn default_gravity_softening() -> f32 {
+    0.05
+}
+
+// Same timeline as TimeLoop::new(), 22 minutes with the sun dying in the last three
+fn default_loop_length() -> f32 {
+    22.0 * 60.0
+}
+
+fn default_expansion_start() -> f32 {
+    19.0 * 60.0
+}
+
+fn default_collapse_start() -> f32 {
+    21.5 * 60.0
+}
+
+fn default_supernova_start() -> f32 {
+    21.6 * 60.0
+}
+
+fn default_giant_scale() -> f32 {
+    1.8
+}
+
+fn default_collapsed_scale() -> f32 {
+    0.2
+}
+
+fn default_shockwave_speed() -> f32 {
+    15.0
+}
+
+fn default_orbit_segments() -> usize {
+    40
+}
+
+// ----- Loading -----
+
+impl Scene {
+    /// Shortens a rewinding step so it stops at the start of the time loop, everything in the
+    /// scene then halts together instead of orbits rewinding past a loop that can't.
+    pub fn clamp_step(&self, delta_time: f32) -> f32 {
+        match &self.time_loop {
+            Some(time_loop) => time_loop.clamp_step(delta_time),
+            None => delta_time,
+        }
+    }
+
+    /// Advances every orbit and spin by `delta_time` seconds, which may be negative to rewind
+    /// once it went through `clamp_step`, and propagates transforms down the scene graph.
+    /// Parents always come before their children in `models`, so one pass is enough.
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(time_loop) = &mut self.time_loop {
+            if time_loop.advance(delta_time) {
+                self.reset();
+                return;
+            }
+            let scale = time_loop.sun_scale();
+            if let Some(sun) = self.models[time_loop.sun].as_any_mut().downcast_mut::<Planet>() {
+                sun.resize(scale);
+            }
+        }
+
+        if let Some(gravity) = &mut self.gravity {
+            gravity.advance(delta_time);
+        }
+
+        let mut frames: Vec<Mat4> = Vec::with_capacity(self.models.len());
+
+        for (index, model) in self.models.iter_mut().enumerate() {
+            if let Some(planet) = model.as_any_mut().downcast_mut::<Planet>() {
+                planet.parent_frame = planet.parent.map_or_else(Mat4::identity, |parent| frames[parent]);
+                let previous_position = planet.position;
+                match self.gravity.as_ref().and_then(|gravity| gravity.position_of(index)) {
+                    Some(position) => planet.set_position(position),
+                    None => planet.translate(delta_time),
+                }
+                planet.rotate(delta_time);
+                if delta_time != 0.0 {
+                    planet.velocity = (planet.position - previous_position) / delta_time;
+                }
+            }
+            frames.push(model.get_frame());
+        }
+    }
+
+    /// Puts orbits, the ship and the camera back where the scene file started them.
+    /// The camera stays in whatever mode it was switched to.
+    pub fn reset(&mut self) {
+        let mode = self.camera.mode;
+        self.camera = self.initial.camera.clone();
+        if self.camera.mode != mode {
+            self.camera.set_mode(mode);
+            self.camera.snap();
+        }
+        self.gravity = self.initial.gravity.clone();
+
+        for (model, initial) in self.models.iter_mut().zip(&self.initial.planets) {
+            if let (Some(planet), Some((orbit, rotation))) = (model.as_any_mut().downcast_mut::<Planet>(), initial) {
+                planet.orbit = *orbit;
+                planet.rotation = *rotation;
+                planet.velocity = Vec3::zeros();
+            }
+        }
+
+        if let Some(ship) = self.models[0].as_any_mut().downcast_mut::<Ship>() {
+            ship.position = self.initial.ship_position;
+            ship.orientation = self.initial.ship_orientation;
+            ship.velocity = Vec3::zeros();
+            ship.angular_velocity = Vec3::zeros();
+        }
+
+        // A zero step places every body back on its orbit and restores the sun's size
+        self.update(0.0);
+    }
+
+    /// Shadow casters for this frame, every body but the ship.
+    pub fn occluders(&self) -> Vec<Occluder> {
+        self.models.iter().skip(1).map(|model| Occluder::from_model(model.as_ref())).collect()
+    }
+
+    /// Where the time loop stands, `Default` (a calm sun) for scenes without one.
+    pub fn loop_state(&self) -> LoopState {
+        match &self.time_loop {
+            Some(time_loop) => time_loop.state(self.models[time_loop.sun].get_position()),
+            None => LoopState::default(),
+        }
+    }
+
+    /// Moves the ship (`models[0]`) by `delta_time` seconds of thrust, gravity and assists.
+    pub fn fly_ship(&mut self, controls: &ShipControls, delta_time: f32) {
+        let Some((ship, bodies)) = self.models.split_first_mut() else {
+            return;
+        };
+        let Some(ship) = ship.as_any_mut().downcast_mut::<Ship>() else {
+            return;
+        };
+
+        let acceleration = match self.gravity.as_ref().filter(|_| ship.gravity) {
+            Some(gravity) => gravity.acceleration_at(&ship.position),
+            None => Vec3::zeros(),
+        };
+
+        // Match velocity locks onto whichever body's surface is closest
+        let target_velocity = bodies
+            .iter()
+            .filter_map(|model| model.as_any().downcast_ref::<Planet>())
+            .min_by(|a, b| {
+                let distance = |planet: &Planet| (planet.position - ship.position).norm() - planet.collision_radius;
+                distance(a).total_cmp(&distance(b))
+            })
+            .map_or_else(Vec3::zeros, |planet| planet.velocity);
+
+        ship.fly(controls, acceleration, target_velocity, delta_time);
+    }
+
+    /// Finds every overlapping pair of models and pushes the ship out of the bodies it hit,
+    /// bouncing off or landing on them. Returns all overlaps, with `bounced` set for ship hits.
+    pub fn collide(&mut self, delta_time: f32) -> Vec<CollisionEvent> {
+        // Friction only drags the ship along while time runs forwards
+        let delta_time = delta_time.max(0.0);
+        let mut events = detect_collisions(&self.models);
+
+        resolve_ship_collisions(&mut self.models, &mut events, delta_time);
+        events
+    }
+
+    /// Display name of a model for logs, the ship or the planet's name from the scene file.
+    pub fn model_name(&self, index: usize) -> &str {
+        match self.models[index].as_any().downcast_ref::<Planet>() {
+            Some(planet) => &planet.name,
+            None => "ship",
+        }
+    }
+
+    /// Reads, validates and instantiates the scene described by the TOML file at `path`.
+    pub fn load(path: &Path) -> Result<Scene, SceneError> {
+        Scene::load_with_registry(path, &ShaderRegistry::builtin())
+    }
+
+    /// Same as `load`, resolving shader names against a custom registry.
+    pub fn load_with_registry(path: &Path, registry: &ShaderRegistry) -> Result<Scene, SceneError> {
+        let description = SceneDescription::load(path)?;
+        description.validate(registry)?;
+        description.build(registry)
+    }
+}
+
+impl SceneDescription {
+    pub fn load(path: &Path) -> Result<SceneDescription, SceneError> {
+        let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        toml::from_str(&source).map_err(|source| SceneError::Parse {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// Checks every reference and value up front so a broken scene fails before any mesh is loaded.
+    pub fn validate(&self, registry: &ShaderRegistry) -> Result<(), SceneError> {
+        check_body(registry, "ship", &self.ship.mesh, &self.ship.shader, self.ship.scale, self.ship.collision_radius)?;
+
+        if self.ship.mass <= 0.0 || self.ship.thrust < 0.0 || self.ship.turn_rate < 0.0 || self.ship.angular_damping < 0.0 {
+            return Err(SceneError::InvalidValue {
+                body: "ship".to_string(),
+                message: "mass must be positive, thrust, turn_rate and angular_damping must not be negative".to_string(),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.ship.restitution) || self.ship.friction < 0.0 {
+            return Err(SceneError::InvalidValue {
+                body: "ship".to_string(),
+                message: "restitution must be in [0, 1] and friction must not be negative".to_string(),
+            });
+        }
+        if self.ship.gravity && self.gravity.is_none() {
+            return Err(SceneError::InvalidValue {
+                body: "ship".to_string(),
+                message: "gravity only has an effect with a [gravity] table".to_string(),
+            });
+        }
+
+        for (index, planet) in self.planets.iter().enumerate() {
+            check_body(registry, &planet.name, &planet.mesh, &planet.shader, planet.scale, planet.collision_radius)?;
+
+            // Parents, the focus and the sun are looked up by name, so names have to be unique
+            if planet.name == "ship" {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: "'ship' is reserved for the player's ship".to_string(),
+                });
+            }
+            if self.planets[..index].iter().any(|other| other.name == planet.name) {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: "another planet already has this name".to_string(),
+                });
+            }
+
+            if let Some(parent) = &planet.parent {
+                if !self.planets[..index].iter().any(|other| &other.name == parent) {
+                    return Err(SceneError::InvalidValue {
+                        body: planet.name.clone(),
+                        message: format!("parent '{}' must be the name of a planet listed before it", parent),
+                    });
+                }
+            }
+
+            if planet.mass < 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: format!("mass must not be negative, got {}", planet.mass),
+                });
+            }
+            if planet.mass > 0.0 && self.gravity.is_none() {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: "mass only has an effect with a [gravity] table".to_string(),
+                });
+            }
+            if planet.mass > 0.0 && planet.parent.is_some() {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: "bodies moved by gravity can't have a parent".to_string(),
+                });
+            }
+
+            if let Some(atmosphere) = &planet.atmosphere {
+                if atmosphere.radius <= planet.scale
+                    || atmosphere.rayleigh.iter().any(|&coefficient| coefficient < 0.0)
+                    || atmosphere.mie < 0.0
+                    || atmosphere.intensity < 0.0
+                    || !(-1.0..1.0).contains(&atmosphere.mie_direction) {
+                    return Err(SceneError::InvalidValue {
+                        body: planet.name.clone(),
+                        message: "atmosphere radius must be larger than the scale, coefficients and intensity must not be negative and mie_direction must be in (-1, 1)".to_string(),
+                    });
+                }
+            }
+
+            if planet.orbit_radius < 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: format!("orbit_radius must not be negative, got {}", planet.orbit_radius),
+                });
+            }
+
+            if !(0.0..1.0).contains(&planet.eccentricity) {
+                return Err(SceneError::InvalidValue {
+                    body: planet.name.clone(),
+                    message: format!("eccentricity must be in [0, 1) for a closed orbit, got {}", planet.eccentricity),
+                });
+            }
+        }
+
+        if let Some(time_loop) = &self.time_loop {
+            if !self.planets.iter().any(|planet| planet.name == time_loop.sun) {
+                return Err(SceneError::InvalidValue {
+                    body: "time_loop".to_string(),
+                    message: format!("sun '{}' is not the name of a planet", time_loop.sun),
+                });
+            }
+            let phases = [0.0, time_loop.expansion_start, time_loop.collapse_start, time_loop.supernova_start, time_loop.length];
+            if phases.windows(2).any(|pair| pair[0] > pair[1]) || time_loop.length <= 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: "time_loop".to_string(),
+                    message: "expects 0 <= expansion_start <= collapse_start <= supernova_start <= length, with a positive length".to_string(),
+                });
+            }
+            if time_loop.giant_scale <= 0.0 || time_loop.collapsed_scale <= 0.0 || time_loop.shockwave_speed < 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: "time_loop".to_string(),
+                    message: "giant_scale and collapsed_scale must be positive, shockwave_speed must not be negative".to_string(),
+                });
+            }
+        }
+
+        for pass in &self.post_processing {
+            pass.validate().map_err(|message| SceneError::InvalidValue {
+                body: "post_processing".to_string(),
+                message,
+            })?;
+        }
+
+        if let Some(gravity) = &self.gravity {
+            if gravity.substep <= 0.0 || gravity.constant < 0.0 || gravity.softening < 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: "gravity".to_string(),
+                    message: "substep must be positive, constant and softening must not be negative".to_string(),
+                });
+            }
+        }
+
+        for light in &self.lights {
+            let (intensity, direction, radius) = match light {
+                LightDescription::Point { intensity, radius, .. } => (*intensity, None, *radius),
+                LightDescription::Directional { intensity, direction, .. } => (*intensity, Some(to_vec3(*direction)), 0.0),
+            };
+            if intensity < 0.0 || radius < 0.0 {
+                return Err(SceneError::InvalidValue {
+                    body: "light".to_string(),
+                    message: format!("intensity and radius must not be negative, got {} and {}", intensity, radius),
+                });
+            }
+            if direction.is_some_and(|direction| direction.norm() == 0.0) {
+                return Err(SceneError::InvalidValue {
+                    body: "light".to_string(),
+                    message: "directional light needs a non-zero direction".to_string(),
+                });
+            }
+        }
+
+        if self.camera.min_radius > self.camera.max_radius {
+            return Err(SceneError::InvalidValue {
+                body: "camera".to_string(),
+                message: "min_radius must not exceed max_radius".to_string(),
+            });
+        }
+        if let Some(focus) = &self.camera.focus {
+            if focus != "ship" && !self.planets.iter().any(|planet| &planet.name == focus) {
+                return Err(SceneError::InvalidValue {
+                    body: "camera".to_string(),
+                    message: format!("focus '{}' is neither \"ship\" nor the name of a planet", focus),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn build(&self, registry: &ShaderRegistry) -> Result<Scene, SceneError> {
+        // Meshes shared between bodies are only loaded once
+        let mut meshes: HashMap<String, Arc<Vec<Vertex>>> = HashMap::new();
+
+        let mut camera = Camera::new(
+            to_vec3(self.camera.eye),
+            to_vec3(self.camera.center),
+            to_vec3(self.camera.up),
+            self.camera.min_radius,
+            self.camera.max_radius,
+            to_vec3(self.camera.bird_view_eye),
+            to_vec3(self.camera.bird_view_center),
+        );
+        if self.camera.mode != CameraMode::Orbit {
+            camera.set_mode(self.camera.mode);
+            camera.snap();
+        }
+
+        let skybox = Skybox::new(
+            self.skybox.stars,
+            self.skybox.distance,
+            to_color(self.skybox.stars_color),
+            to_color(self.skybox.space_color),
+        );
+
+        let mut models: Vec<Box<dyn Model>> = Vec::with_capacity(self.planets.len() + 1);
+
+        let mut ship = Ship::new(
+            load_mesh(&mut meshes, &self.ship.mesh)?,
+            create_shader(registry, "ship", &self.ship.shader, &self.ship.shader_params)?,
+            to_vec3(self.ship.position),
+            self.ship.scale,
+            to_vec3(self.ship.rotation),
+            self.ship.collision_radius,
+        );
+        ship.mass = self.ship.mass;
+        ship.thrust = self.ship.thrust;
+        ship.turn_rate = self.ship.turn_rate;
+        ship.angular_damping = self.ship.angular_damping;
+        ship.gravity = self.ship.gravity;
+        ship.restitution = self.ship.restitution;
+        ship.friction = self.ship.friction;
+        models.push(Box::new(ship));
+
+        let mut gravity_bodies = Vec::new();
+
+        for planet in &self.planets {
+            let mut body = Planet::new(
+                load_mesh(&mut meshes, &planet.mesh)?,
+                planet.scale,
+                create_shader(registry, &planet.name, &planet.shader, &planet.shader_params)?,
+                Orbit {
+                    semi_major_axis: planet.orbit_radius,
+                    eccentricity: planet.eccentricity,
+                    inclination: planet.inclination,
+                    ascending_node: planet.ascending_node,
+                    periapsis_argument: planet.periapsis_argument,
+                    mean_anomaly: planet.orbit_angle,
+                    mean_motion: planet.orbit_speed,
+                },
+                planet.collision_radius,
+                to_vec3(planet.center),
+                planet.orbit_segments,
+            );
+            body.name = planet.name.clone();
+            body.material = Material {
+                blend: planet.blend,
+                depth_write: planet.depth_write.unwrap_or(planet.blend == BlendMode::Opaque),
+            };
+            body.atmosphere = planet.atmosphere.as_ref().map(|atmosphere| atmosphere.build(planet.scale));
+            body.axial_tilt = planet.axial_tilt;
+            body.spin_period = planet.spin_period;
+            body.rotate(0.0);
+            // models[0] is the ship, so planet `i` lives at models[i + 1]
+            body.parent = planet.parent.as_ref().and_then(|parent| {
+                self.planets.iter().position(|other| &other.name == parent).map(|index| index + 1)
+            });
+            if planet.mass > 0.0 {
+                // The orbit only places the body, from then on gravity decides where it goes
+                gravity_bodies.push(GravityBody::new(models.len(), planet.mass, body.position, to_vec3(planet.velocity)));
+                body.orbit_segments.clear();
+            }
+            models.push(Box::new(body));
+        }
+
+        let lights = self.lights.iter().map(LightDescription::build).collect();
+
+        let gravity = self.gravity.as_ref().map(|gravity| {
+            GravitySimulation::new(gravity_bodies, gravity.constant, gravity.substep, gravity.softening)
+        });
+
+        let time_loop = self.time_loop.as_ref().map(|description| {
+            let sun = self.planets.iter().position(|planet| planet.name == description.sun).unwrap_or(0) + 1;
+            let mut time_loop = TimeLoop::new(sun, models[sun].get_scale());
+            time_loop.length = description.length;
+            time_loop.expansion_start = description.expansion_start;
+            time_loop.collapse_start = description.collapse_start;
+            time_loop.supernova_start = description.supernova_start;
+            time_loop.giant_scale = description.giant_scale;
+            time_loop.collapsed_scale = description.collapsed_scale;
+            time_loop.shockwave_speed = description.shockwave_speed;
+            time_loop
+        });
+
+        if let Some(focus) = &self.camera.focus {
+            // Models are the ship followed by the planets in file order
+            let focus = self.planets.iter().position(|planet| &planet.name == focus).map_or(0, |index| index + 1);
+            camera.focus_on(focus, bounding_radius(models[focus].as_ref()));
+        }
+
+        let post_processing = PostProcessing::new(self.post_processing.iter().map(PostPassDescription::build).collect());
+
+        let initial = InitialState::capture(&camera, &models, &gravity);
+
+        Ok(Scene { camera, skybox, models, lights, gravity, time_loop, post_processing, initial })
+    }
+}
+
+fn check_body(
+    registry: &ShaderRegistry,
+    body: &str,
+    mesh: &str,
+    shader: &str,
+    scale: f32,
+    collision_radius: f32,
+) -> Result<(), SceneError> {
+    if !registry.contains(shader) {
+        return Err(SceneError::Shader {
+            body: body.to_string(),
+            source: ShaderError::UnknownShader {
+                name: shader.to_string(),
+                available: registry.names(),
+            },
+        });
+    }
+    if !Path::new(mesh).is_file() {
+        return Err(SceneError::MissingMesh {
+            body: body.to_string(),
+            path: mesh.to_string(),
+        });
+    }
+    if scale <= 0.0 {
+        return Err(SceneError::InvalidValue {
+            body: body.to_string(),
+            message: format!("scale must be positive, got {}", scale),
+        });
+    }
+    if collision_radius < 0.0 {
+        return Err(SceneError::InvalidValue {
+            body: body.to_string(),
+            message: format!("collision_radius must not be negative, got {}", collision_radius),
+        });
+    }
+    Ok(())
+}
+
+fn create_shader(
+    registry: &ShaderRegistry,
+    body: &str,
+    shader: &str,
+    params: &toml::Table,
+) -> Result<Box<dyn FragmentShader>, SceneError> {
+    registry
+        .create(shader, &ShaderParams::new(params.clone()))
+        .map_err(|source| SceneError::Shader {
+            body: body.to_string(),
+            source,
+        })
+}
+
+fn load_mesh(meshes: &mut HashMap<String, Arc<Vec<Vertex>>>, path: &str) -> Result<Arc<Vec<Vertex>>, SceneError> {
+    if let Some(vertices) = meshes.get(path) {
+        return Ok(vertices.clone());
+    }
+
+    let obj = Obj::load(path).map_err(|source| SceneError::InvalidMesh {
+        path: path.to_string(),
+        source,
+    })?;
+    let vertices = Arc::new(obj.get_vertex_array());
+    meshes.insert(path.to_string(), vertices.clone());
+
+    Ok(vertices)
+}
+
+fn to_vec3(values: [f32; 3]) -> Vec3 {
+    Vec3::new(values[0], values[1], values[2])
+}
+
+fn to_color(values: [u8; 3]) -> Color {
+    Color::new(values[0], values[1], values[2])
+}
@@ -1,61 +1,24 @@
-use once_cell::sync::Lazy;
+use fastnoise_lite::FastNoiseLite;
 use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4};
-use std::sync::Mutex;
 
 use super::entity::vertex::Vertex;
 use super::entity::fragment::Fragment;
+use super::noise::{FractalKind, NoiseConfig, NoiseKind};
 use super::render::Uniforms;
 use super::entity::color::Color;
+use super::shader_registry::{ShaderError, ShaderParams};
 
-use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
-
-static SUN_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::Cellular)); // Use Cellular noise for texture-like patterns
-  noise.set_frequency(Some(10.0)); // Decrease frequency for bigger cells (larger scale)
-  Mutex::new(noise) // Wrap the noise generator in a Mutex
-});
-
-static EARTH_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::Value)); // Using Perlin noise for smooth height maps
-  noise.set_frequency(Some(10.0)); // Low frequency for large terrain features
-  Mutex::new(noise) // Wrap the noise generator in a Mutex
-});
-
-// New Cloud Generator
-static CLOUDS_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::OpenSimplex2S)); // Use Simplex noise for smooth cloud patterns
-  noise.set_frequency(Some(0.8)); // Adjust frequency for cloud density
-  Mutex::new(noise)
-});
-
-static CRATER_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::OpenSimplex2S)); // Use OpenSimplex2S for smooth crater patterns
-  noise.set_frequency(Some(1.0)); // Adjust frequency for crater density
-  Mutex::new(noise)
-});
-
-static CHIP_PATH_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::Perlin)); // Use Perlin noise for smoother patterns
-  noise.set_frequency(Some(10.0)); // Adjust frequency for smaller, detailed paths
-  Mutex::new(noise)
-});
-
-static ADDITIONAL_PATH_GENERATOR: Lazy<Mutex<FastNoiseLite>> = Lazy::new(|| {
-  let mut noise = FastNoiseLite::new();
-  noise.set_noise_type(Some(NoiseType::OpenSimplex2S)); // OpenSimplex2S for smooth patterns
-  noise.set_frequency(Some(3.0)); // Adjust frequency for detail
-  noise.set_fractal_type(Some(FractalType::PingPong)); // Using the PingPong fractal
-  noise.set_fractal_octaves(Some(1)); // Control the octaves for more complexity
-  noise.set_fractal_ping_pong_strength(Some(2.0));
-  Mutex::new(noise)
-});
-
+/// A configured fragment shader. Instances are shared by every rasterizer thread,
+/// so shading must only read `self`.
+pub trait FragmentShader: Send + Sync {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color;
 
+  /// Light the surface gives off on its own, as a multiple of its color, on top of what
+  /// the scene lights reflect off it. 0 for bodies that only reflect light.
+  fn emission(&self) -> f32 {
+    0.0
+  }
+}
 
 pub fn vertex_shader(vertex: &Vertex, transformation_matrix: &Mat4, uniforms: &Uniforms) -> Vertex {
   // Transform position
@@ -66,34 +29,19 @@ pub fn vertex_shader(vertex: &Vertex, transformation_matrix: &Mat4, uniforms: &U
     1.0
   );
 
-  let clip_pos = transformation_matrix * position;
-
-  // Perform perspective division
-  let w = clip_pos.w;
-  let ndc_position = Vec4::new(
-    clip_pos.x / w,
-    clip_pos.y / w,
-    clip_pos.z / w,
-    1.0);
+  // Stay in homogeneous clip space: the perspective divide happens after clipping,
+  // see `clipping::project_to_screen`
+  let clip_position = transformation_matrix * position;
 
-  // Transform normal
-  let screen_position = uniforms.viewport_matrix * ndc_position;
-  let transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+  let world_position = (uniforms.model_matrix * position).xyz();
 
-  // Transform normal
-  let vertex_normal = Vec4::new(vertex.normal.x, vertex.normal.y, vertex.normal.z, 1.0);
+  // Transform normal to world space, as a direction (w = 0) so translation doesn't leak in
+  let vertex_normal = Vec4::new(vertex.normal.x, vertex.normal.y, vertex.normal.z, 0.0);
   let normal_matrix = uniforms.model_matrix
       .try_inverse()
       .unwrap_or(Mat4::identity())
       .transpose();
-  let transformed_normal = normal_matrix * vertex_normal;
-  let w = transformed_normal.w;
-  let transformed_normal = Vec3::new(
-      transformed_normal.x / w,
-      transformed_normal.y / w,
-      transformed_normal.z / w,
-  );
-
+  let transformed_normal = (normal_matrix * vertex_normal).xyz().normalize();
 
   // Create a new Vertex with transformed attributes
   Vertex {
@@ -101,225 +49,388 @@ pub fn vertex_shader(vertex: &Vertex, transformation_matrix: &Mat4, uniforms: &U
     normal: vertex.normal,
     tex_coords: vertex.tex_coords,
     color: vertex.color,
-    frustrum_position: ndc_position,
-    transformed_position,
-    transformed_normal: transformed_normal,
+    clip_position,
+    frustrum_position: Vec4::zeros(),
+    transformed_position: Vec3::zeros(),
+    transformed_normal,
+    world_position,
   }
 }
 
-pub fn simple_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  fragment.color * fragment.intensity
+// ----- Simple -----
+
+pub struct SimpleShader;
+
+impl SimpleShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&[])?;
+    Ok(SimpleShader)
+  }
 }
 
-pub fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-      // Lock the Mutex to get a mutable reference to the noise generator
-      let noise = SUN_GENERATOR.lock().unwrap();
-
-      // Slow down the passage of time by scaling the time value
-      let time_factor = (uniforms.time as f32) / 2.0; // Slow down time progression
-      
-      // Instead of resetting the seed every time, displace it around the current noise
-      let displacement = time_factor * 0.05; // Small displacement factor to smooth the noise evolution
-  
-      // Displace the noise coordinates slightly over time
-      let noise_x = noise.get_noise_2d(fragment.vertex_position.x + displacement, fragment.vertex_position.y + displacement);
-      let noise_y = noise.get_noise_2d(fragment.vertex_position.x + 0.5, fragment.vertex_position.y + 0.5);
-      
-      // Combine noise for more variation
-      let noise_factor = (noise_x + noise_y) * 0.5;
-      
-      // Compute intensity based only on the noise factor
-      let intensity = 1.0 + noise_factor * 0.3; // Increased noise factor to make the pattern more pronounced
-      
-      // Define the yellow, orange, and white colors for blending
-      let yellow = Color::new(255, 186, 3); // Bright yellow
-      let orange = Color::new(200, 50, 0); // Darker orange
-      let white = Color::new(255, 255, 255); // White for the lighter parts
-      
-      // Blend between yellow/orange and white, depending on the intensity
-      // If intensity is high, blend to white, else blend to orange
-      let color = if intensity > 1.0 {
-          yellow.lerp(&white, intensity - 1.0) // Blend from yellow to white
-      } else {
-          orange.lerp(&white, 1.0 - intensity) // Blend from orange to white
-      };
-  
-      color
+impl FragmentShader for SimpleShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    fragment.color
+  }
 }
 
-pub fn earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-      // Lock the Mutex to get a mutable reference to the Earth noise generator
-      let noise = EARTH_GENERATOR.lock().unwrap();
-
-      // Lock the Mutex to get a mutable reference to the Clouds noise generator
-      let clouds_noise = CLOUDS_GENERATOR.lock().unwrap();
-  
-      // Slow down the passage of time by scaling the time value
-      let time_factor = (uniforms.time as f32) / 10.0; // Slow down time progression
-      
-      // Displacement for Earth texture
-      let displacement = time_factor * 0.05; // Small displacement factor to smooth the noise evolution
-      let noise_value = noise.get_noise_2d(fragment.vertex_position.x + displacement, fragment.vertex_position.y);
-      
-      // Cloud texture displacement (clouds move slightly faster than the Earth texture)
-      let cloud_displacement = time_factor * 0.3; // Clouds move faster for more dynamic effect
-      let cloud_noise_value = clouds_noise.get_noise_2d(fragment.vertex_position.x + cloud_displacement, fragment.vertex_position.y);
-  
-      // The noise value can represent height, so map it to the terrain color
-      let ocean_level = 0.0; // Ocean is at noise value 0.0
-      let terrain_level = 0.5; // Terrain starts at noise value 0.5 (adjustable)
-    
-      // Define the blue (ocean) and green (terrain) colors
-      let ocean_color = Color::new(0, 0, 255); // Blue for the ocean
-      let terrain_color = Color::new(34, 139, 34); // Green for the terrain
-      
-      // Blend between ocean and terrain based on noise value (height)
-      let earth_color = if noise_value < ocean_level {
-          ocean_color // Blue for ocean
-      } else if noise_value < terrain_level {
-          // Blend between blue and green for shallow water/shoreline
-          ocean_color.lerp(&terrain_color, (noise_value - ocean_level) / (terrain_level - ocean_level))
-      } else {
-          terrain_color // Green for terrain
-      };
-  
-      // Cloud effect: The higher the cloud noise value, the less visible the clouds
-      let cloud_opacity = (cloud_noise_value - 0.2).abs() ; // Adjust cloud opacity based on the noise value (max 1.0)
-  
-      // Define the cloud color as white with some transparency
-      let cloud_color = Color::new(255, 255, 255); // White for clouds
-  
-      // Blend the cloud color with the Earth color based on cloud opacity
-      let final_color = earth_color.lerp(&cloud_color, cloud_opacity);
-  
-      // Multiply by intensity for lighting effects
-      final_color * fragment.intensity.max(0.4)
+// ----- Sun -----
+
+pub struct SunShader {
+  pub noise: FastNoiseLite,
+  pub bright_color: Color,
+  pub dark_color: Color,
+  pub hot_color: Color,
+  pub giant_color: Color,    // Tint of the red giant at the end of the loop
+  pub collapse_color: Color, // Tint while collapsing right before the supernova
+  pub brightness: f32,       // Emission, so the surface outshines lit bodies once tone mapped
 }
 
-pub fn crater_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  // Lock the Mutex to get a mutable reference to the Earth and crater noise generators
-  let noise = EARTH_GENERATOR.lock().unwrap();
-  let crater_noise = CRATER_GENERATOR.lock().unwrap();
-
-  // Slow down the passage of time by scaling the time value
-  let time_factor = (uniforms.time as f32) / 10.0; // Slow down time progression
-  
-  // Displacement for general surface texture (smoothly moving over time)
-  let displacement = time_factor * 0.05; // Small displacement factor to smooth the noise evolution
-  let surface_noise_value = noise.get_noise_2d(fragment.vertex_position.x + displacement, fragment.vertex_position.y);
-
-  // Displacement for crater texture (to make craters move differently than the surface)
-  let crater_displacement = time_factor * 0.05; // Craters move slower for a more stable look
-  let crater_noise_value = crater_noise.get_noise_2d(fragment.vertex_position.x + crater_displacement, fragment.vertex_position.y);
-
-  // The noise value can represent height, so map it to the terrain color
-  let surface_level = 0.0; // Base surface level
-  let crater_level = -0.3; // Crater depth level
-
-  // Define colors for surface and craters
-  let surface_color = Color::new(160, 160, 160); // Light grey for the general surface
-  let crater_color = Color::new(90, 90, 90); // Dark grey for craters
-
-  // Blend between surface and crater based on noise value (height)
-  let base_color = if surface_noise_value > surface_level {
-      surface_color // Surface color
-  } else {
+impl SunShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["noise", "bright_color", "dark_color", "hot_color", "giant_color", "collapse_color", "brightness"])?;
+    Ok(SunShader {
+      // Cellular noise for texture-like patterns, high frequency for small cells
+      noise: params.noise("noise", NoiseConfig::new(NoiseKind::Cellular, 10.0))?.build(),
+      bright_color: params.color("bright_color", Color::new(255, 186, 3))?, // Bright yellow
+      dark_color: params.color("dark_color", Color::new(200, 50, 0))?,      // Darker orange
+      hot_color: params.color("hot_color", Color::new(255, 255, 255))?,     // White for the lighter parts
+      giant_color: params.color("giant_color", Color::new(200, 30, 10))?,   // Deep red
+      collapse_color: params.color("collapse_color", Color::new(170, 200, 255))?, // Blue white
+      brightness: params.non_negative("brightness", 8.0)?,
+    })
+  }
+}
+
+impl FragmentShader for SunShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let noise = &self.noise;
+
+    // Slow down the passage of time by scaling the time value
+    let time_factor = uniforms.time / 2.0; // Slow down time progression
+
+    // Instead of resetting the seed every time, displace it around the current noise
+    let displacement = time_factor * 0.05; // Small displacement factor to smooth the noise evolution
+
+    // Displace the noise coordinates slightly over time
+    let noise_x = noise.get_noise_2d(fragment.vertex_position.x + displacement, fragment.vertex_position.y + displacement);
+    let noise_y = noise.get_noise_2d(fragment.vertex_position.x + 0.5, fragment.vertex_position.y + 0.5);
+
+    // Combine noise for more variation
+    let noise_factor = (noise_x + noise_y) * 0.5;
+
+    // Compute intensity based only on the noise factor
+    let intensity = 1.0 + noise_factor * 0.3; // Increased noise factor to make the pattern more pronounced
+
+    // Blend between yellow/orange and white, depending on the intensity
+    // If intensity is high, blend to white, else blend to orange
+    let surface = if intensity > 1.0 {
+      self.bright_color.lerp(&self.hot_color, intensity - 1.0) // Blend from yellow to white
+    } else {
+      self.dark_color.lerp(&self.hot_color, 1.0 - intensity) // Blend from orange to white
+    };
+
+    // Age the sun along the time loop: red giant, collapse, then a white flash that fades
+    let state = &uniforms.time_loop;
+    let aged = surface
+      .lerp(&self.giant_color, state.expansion * 0.8)
+      .lerp(&self.collapse_color, state.collapse);
+    if state.supernova > 0.0 {
+      aged.lerp(&self.hot_color, 1.0 - state.supernova)
+    } else {
+      aged
+    }
+  }
+
+  fn emission(&self) -> f32 {
+    self.brightness
+  }
+}
+
+// ----- Earth -----
+
+pub struct EarthShader {
+  pub terrain_noise: FastNoiseLite,
+  pub cloud_noise: FastNoiseLite,
+  pub ocean_color: Color,
+  pub terrain_color: Color,
+  pub cloud_color: Color,
+}
+
+impl EarthShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["terrain_noise", "cloud_noise", "ocean_color", "terrain_color", "cloud_color"])?;
+    Ok(EarthShader {
+      // Value noise for the terrain height map, smooth simplex for the clouds
+      terrain_noise: params.noise("terrain_noise", NoiseConfig::new(NoiseKind::Value, 10.0))?.build(),
+      cloud_noise: params.noise("cloud_noise", NoiseConfig::new(NoiseKind::OpenSimplex2s, 0.8))?.build(),
+      ocean_color: params.color("ocean_color", Color::new(0, 0, 255))?,        // Blue for the ocean
+      terrain_color: params.color("terrain_color", Color::new(34, 139, 34))?,  // Green for the terrain
+      cloud_color: params.color("cloud_color", Color::new(255, 255, 255))?,    // White for clouds
+    })
+  }
+}
+
+impl FragmentShader for EarthShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Terrain height map and cloud cover
+    let noise = &self.terrain_noise;
+    let clouds_noise = &self.cloud_noise;
+
+    // Terrain is fixed to the surface, the planet's spin carries it around
+    let noise_value = noise.get_noise_2d(fragment.vertex_position.x, fragment.vertex_position.y);
+
+    // Cloud texture displacement (clouds move slightly faster than the Earth texture)
+    let cloud_displacement = uniforms.time * 0.03;
+    let cloud_noise_value = clouds_noise.get_noise_2d(fragment.vertex_position.x + cloud_displacement, fragment.vertex_position.y);
+
+    // The noise value can represent height, so map it to the terrain color
+    let ocean_level = 0.0; // Ocean is at noise value 0.0
+    let terrain_level = 0.5; // Terrain starts at noise value 0.5 (adjustable)
+
+    // Blend between ocean and terrain based on noise value (height)
+    let earth_color = if noise_value < ocean_level {
+      self.ocean_color // Blue for ocean
+    } else if noise_value < terrain_level {
+      // Blend between blue and green for shallow water/shoreline
+      self.ocean_color.lerp(&self.terrain_color, (noise_value - ocean_level) / (terrain_level - ocean_level))
+    } else {
+      self.terrain_color // Green for terrain
+    };
+
+    // Cloud effect: The higher the cloud noise value, the less visible the clouds
+    let cloud_opacity = (cloud_noise_value - 0.2).abs() ; // Adjust cloud opacity based on the noise value (max 1.0)
+
+    // Blend the cloud color with the Earth color based on cloud opacity
+    earth_color.lerp(&self.cloud_color, cloud_opacity)
+  }
+}
+
+// ----- Crater -----
+
+pub struct CraterShader {
+  pub surface_noise: FastNoiseLite,
+  pub crater_noise: FastNoiseLite,
+  pub surface_color: Color,
+  pub crater_color: Color,
+}
+
+impl CraterShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["surface_noise", "crater_noise", "surface_color", "crater_color"])?;
+    Ok(CraterShader {
+      // Value noise for the general surface, simplex for the crater depth
+      surface_noise: params.noise("surface_noise", NoiseConfig::new(NoiseKind::Value, 10.0))?.build(),
+      crater_noise: params.noise("crater_noise", NoiseConfig::new(NoiseKind::OpenSimplex2s, 1.0))?.build(),
+      surface_color: params.color("surface_color", Color::new(160, 160, 160))?, // Light grey for the general surface
+      crater_color: params.color("crater_color", Color::new(90, 90, 90))?,      // Dark grey for craters
+    })
+  }
+}
+
+impl FragmentShader for CraterShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    // General surface and crater depth
+    let noise = &self.surface_noise;
+    let crater_noise = &self.crater_noise;
+
+    // Surface and craters are fixed to the body, the planet's spin carries them around
+    let surface_noise_value = noise.get_noise_2d(fragment.vertex_position.x, fragment.vertex_position.y);
+    let crater_noise_value = crater_noise.get_noise_2d(fragment.vertex_position.x, fragment.vertex_position.y);
+
+    // The noise value can represent height, so map it to the terrain color
+    let surface_level = 0.0; // Base surface level
+    let crater_level = -0.3; // Crater depth level
+
+    // Blend between surface and crater based on noise value (height)
+    if surface_noise_value > surface_level {
+      self.surface_color // Surface color
+    } else {
       // Blend between surface color and crater color based on crater depth
-      surface_color.lerp(&crater_color, (crater_noise_value - crater_level) / (surface_level - crater_level))
-  };
+      self.surface_color.lerp(&self.crater_color, (crater_noise_value - crater_level) / (surface_level - crater_level))
+    }
+  }
+}
+
+// ----- Banded (Saturn and its rings) -----
+
+/// Picks the band color for `value`, negative bands all use the last color.
+fn band_color(bands: &[Color], value: i32) -> Color {
+  let index = value % bands.len() as i32;
+  if index < 0 {
+    bands[bands.len() - 1]
+  } else {
+    bands[index as usize]
+  }
+}
+
+/// Smoothly blends consecutive bands of width `band_width` along `coordinate`.
+fn banded_color(bands: &[Color], band_width: f32, coordinate: f32) -> Color {
+  let stripe_value = (coordinate / band_width).floor() as i32;
 
-  // Adjust the base color by light intensity (shading)
-  let shaded_color = base_color;
+  // Smooth interpolation factor between stripes
+  let transition_factor = (coordinate / band_width).fract().abs();
 
-  // Multiply by intensity for additional lighting effects (to make the texture more dynamic)
-  shaded_color * fragment.intensity.max(0.4)
+  // Interpolate between the current stripe and the next stripe
+  let current_color = band_color(bands, stripe_value);
+  let next_color = band_color(bands, stripe_value + 1);
+  current_color.lerp(&next_color, transition_factor)
 }
 
-pub fn saturn_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    // Define the three stripe colors
-    let color1 = Color::new(210, 180, 140); // Light tan
-    let color2 = Color::new(160, 82, 45);  // Reddish brown
-    let color3 = Color::new(255, 228, 196); // Pale cream
+pub struct SaturnShader {
+  pub bands: Vec<Color>,
+  pub band_width: f32,
+}
 
-    // Control the width of the stripes
-    let stripe_width = 0.1; // Adjust for thinner or thicker bands
+impl SaturnShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["bands", "band_width"])?;
+    Ok(SaturnShader {
+      bands: params.colors("bands", &[
+        Color::new(210, 180, 140), // Light tan
+        Color::new(160, 82, 45),   // Reddish brown
+        Color::new(255, 228, 196), // Pale cream
+      ])?,
+      band_width: params.positive("band_width", 0.1)?, // Adjust for thinner or thicker bands
+    })
+  }
+}
 
+impl FragmentShader for SaturnShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
     // Use the y-position of the vertex to determine the stripe
-    let y_position = fragment.vertex_position.y; // The y-coordinate determines the stripe
-    let stripe_value = (y_position / stripe_width).floor() as i32;
+    banded_color(&self.bands, self.band_width, fragment.vertex_position.y)
+  }
+}
 
-    // Smooth interpolation factor between stripes
-    let transition_factor = (y_position / stripe_width).fract().abs();
+pub struct SaturnRingShader {
+  pub bands: Vec<Color>,
+  pub band_width: f32,
+  pub opacity: f32, // Alpha of the brightest bands, only visible with a translucent `blend`
+}
 
-    // Get the base colors for the current and next stripe
-    let current_color = match stripe_value % 3 {
-        0 => color1,
-        1 => color2,
-        _ => color3,
-    };
+impl SaturnRingShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["bands", "band_width", "opacity"])?;
+    Ok(SaturnRingShader {
+      bands: params.colors("bands", &[
+        Color::new(194, 194, 194), // Light grey
+        Color::new(128, 127, 122), // Dark grey
+        Color::new(242, 228, 196), // Pale cream
+      ])?,
+      band_width: params.positive("band_width", 0.1)?,
+      opacity: params.float("opacity", 1.0)?,
+    })
+  }
+}
 
-    let next_color = match (stripe_value + 1) % 3 {
-        0 => color1,
-        1 => color2,
-        _ => color3,
-    };
+impl FragmentShader for SaturnRingShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    // Ring properties
+    let ring_center = Vec3::new(0.0, 0.0, 0.0); // Center of the ring in model space
+
+    // Compute the radial distance in the XZ plane (ignoring the Y-axis)
+    let fragment_position_xz = fragment.vertex_position.xz(); // Extract x and z
+    let ring_center_xz = ring_center.xz();                   // Extract center x and z
+    let radial_distance = (fragment_position_xz - ring_center_xz).norm(); // Distance in XZ plane
 
-    // Interpolate between the current stripe and the next stripe
-    let base_color = current_color.lerp(&next_color, transition_factor);
+    let base_color = banded_color(&self.bands, self.band_width, radial_distance);
 
-    // Adjust brightness based on the fragment's intensity
-    base_color * fragment.intensity.max(0.4)
+    // Darker bands are thinner, more of what is behind shows through them
+    let luminance = (0.3 * base_color.r as f32 + 0.59 * base_color.g as f32 + 0.11 * base_color.b as f32) / 255.0;
+    base_color.with_alpha(self.opacity * (0.5 + 0.5 * luminance))
+  }
 }
 
-pub fn pluto_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  // Lock the noise generator for craters
-  let noise = CRATER_GENERATOR.lock().unwrap();
+// ----- Pluto -----
 
-  // Define the heart's center position and scale
-  let heart_center = Vec2::new(0.0, -0.2); // Center near the bottom
-  let heart_scale = 0.2; // Scale for a properly-sized heart
+pub struct PlutoShader {
+  pub noise: FastNoiseLite,
+  pub heart_color: Color,
+  pub heart_center: Vec2,
+  pub heart_scale: f32,
+}
+
+impl PlutoShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["noise", "heart_color", "heart_center_x", "heart_center_y", "heart_scale"])?;
+    Ok(PlutoShader {
+      noise: params.noise("noise", NoiseConfig::new(NoiseKind::OpenSimplex2s, 1.0))?.build(),
+      heart_color: params.color("heart_color", Color::new(200, 80, 100))?, // Reddish-pink for the heart
+      heart_center: Vec2::new(
+        params.float("heart_center_x", 0.0)?,
+        params.float("heart_center_y", -0.2)?, // Center near the bottom
+      ),
+      heart_scale: params.positive("heart_scale", 0.2)?, // Scale for a properly-sized heart
+    })
+  }
+}
 
-  // Use noise to create surface details (craters)
-  let noise_value = noise.get_noise_2d(
+impl FragmentShader for PlutoShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    // Noise generator for craters
+    let noise = &self.noise;
+
+    // Use noise to create surface details (craters)
+    let noise_value = noise.get_noise_2d(
       fragment.vertex_position.x,
       fragment.vertex_position.y,
-  );
+    );
 
-  // Map noise to grayscale for the base surface
-  let base_surface = Color::new(
+    // Map noise to grayscale for the base surface
+    let base_surface = Color::new(
       (noise_value * 40.0 + 150.0) as u8, // Light gray
       (noise_value * 50.0 + 150.0) as u8,
       (noise_value * 30.0 + 150.0) as u8,
-  );
-
-  // Calculate the relative position for the heart
-  let relative_pos = Vec2::new(
-      (fragment.vertex_position.x - heart_center.x) / heart_scale,
-      (fragment.vertex_position.y - heart_center.y) / heart_scale,
-  );
-
-  // Improved heart shape formula:
-  // A smoother, fuller heart shape derived from polar cardioid equations
-  // r = 1 - sin(theta), transformed into Cartesian coordinates
-  let x = relative_pos.x;
-  let y = relative_pos.y;
-  let heart_value = (x * x + (5.0 * y / 4.0 - x.abs().sqrt()).powi(2)) - 1.0;
-
-  // Adjust the heart mask to define the heart region
-  let heart_mask = (1.0 - heart_value.abs().min(1.0)).max(0.0); // Clamp to create a smooth mask
+    );
+
+    // Calculate the relative position for the heart
+    let relative_pos = Vec2::new(
+      (fragment.vertex_position.x - self.heart_center.x) / self.heart_scale,
+      (fragment.vertex_position.y - self.heart_center.y) / self.heart_scale,
+    );
+
+    // Improved heart shape formula:
+    // A smoother, fuller heart shape derived from polar cardioid equations
+    // r = 1 - sin(theta), transformed into Cartesian coordinates
+    let x = relative_pos.x;
+    let y = relative_pos.y;
+    let heart_value = (x * x + (5.0 * y / 4.0 - x.abs().sqrt()).powi(2)) - 1.0;
+
+    // Adjust the heart mask to define the heart region
+    let heart_mask = (1.0 - heart_value.abs().min(1.0)).max(0.0); // Clamp to create a smooth mask
+
+    // Blend the heart color and base texture
+    self.heart_color.lerp(&base_surface, 1.0 - heart_mask)
+  }
+}
 
-  // Define the heart color
-  let heart_color = Color::new(200, 80, 100); // Reddish-pink for the heart
+// ----- Vortex -----
 
-  // Blend the heart color and base texture
-  let blended_color = heart_color.lerp(&base_surface, 1.0 - heart_mask);
+pub struct VortexShader {
+  pub noise: FastNoiseLite,
+  pub core_color: Color,
+  pub mid_color: Color,
+  pub edge_color: Color,
+  pub swirl_speed: f32,
+}
 
-  // Apply the fragment's intensity
-  blended_color * fragment.intensity.max(0.4)
+impl VortexShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["noise", "core_color", "mid_color", "edge_color", "swirl_speed"])?;
+    Ok(VortexShader {
+      noise: params.noise("noise", NoiseConfig::new(NoiseKind::OpenSimplex2s, 1.0))?.build(),
+      core_color: params.color("core_color", Color::new(255, 50, 50))?,  // Bright red for the vortex center
+      mid_color: params.color("mid_color", Color::new(120, 60, 240))?,   // Purple for swirling areas
+      edge_color: params.color("edge_color", Color::new(10, 10, 30))?,   // Dark blue for outer regions
+      swirl_speed: params.float("swirl_speed", 0.5)?,
+    })
+  }
 }
 
-pub fn vortex_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let noise = CRATER_GENERATOR.lock().unwrap();
+impl FragmentShader for VortexShader {
+  fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let noise = &self.noise;
 
     // Convert Cartesian coordinates to polar
     let x = fragment.vertex_position.x;
@@ -328,7 +439,7 @@ pub fn vortex_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let mut angle = y.atan2(x); // Angle in radians
 
     // Add a time-based rotation to the angle for swirling
-    angle += uniforms.time * 0.5;
+    angle += uniforms.time * self.swirl_speed;
 
     // Convert back to Cartesian coordinates for distortion
     let swirl_x = radius * angle.cos();
@@ -338,81 +449,47 @@ pub fn vortex_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let noise_value = noise.get_noise_2d(swirl_x, swirl_y);
 
     // Map the noise value to a color gradient
-    let core_color = Color::new(255, 50, 50); // Bright red for the vortex center
-    let mid_color = Color::new(120, 60, 240); // Purple for swirling areas
-    let edge_color = Color::new(10, 10, 30); // Dark blue for outer regions
-
-    let color = if radius < 0.5 {
-        core_color.lerp(&mid_color, noise_value * 0.5 + 0.5) // Blend from red to purple
+    if radius < 0.5 {
+      self.core_color.lerp(&self.mid_color, noise_value * 0.5 + 0.5) // Blend from red to purple
     } else {
-        mid_color.lerp(&edge_color, (radius - 0.5).min(1.0)) // Blend from purple to blue
-    };
-
-    color * fragment.intensity.max(0.5)
+      self.mid_color.lerp(&self.edge_color, (radius - 0.5).min(1.0)) // Blend from purple to blue
+    }
+  }
 }
 
-pub fn hypnos_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let noise = ADDITIONAL_PATH_GENERATOR.lock().unwrap();
+// ----- Hypnos -----
 
-  // Slow down the passage of time to create a more subtle animation effect
-  let time_factor = (uniforms.time as f32) / 10.0;
-
-  // Get the PingPong fractal noise based on the fragment's position
-  let noise_value = noise.get_noise_2d(fragment.vertex_position.x * 5.0, fragment.vertex_position.y * 5.0 + time_factor);
-
-  // Normalize the noise value to the range [0, 1]
-  let normalized_noise_value = (noise_value + 1.0) * 0.5;
-
-  // Define the base planet color (dark base color for the planet)
-  let planet_color = Color::new(0, 40, 0); // Dark green color for the planet
-
-  // Define the color for the fractal noise pattern (lighter colors for the swirling effect)
-  let fractal_color = Color::new(255, 255, 0); // Yellow for the swirling effect
-
-  // Blend the planet color with the fractal pattern based on the noise value
-  let final_color = planet_color.lerp(&fractal_color, normalized_noise_value);
-
-  // Apply fragment intensity to control brightness
-  final_color * fragment.intensity.max(0.5)
+pub struct HypnosShader {
+  pub noise: FastNoiseLite,
+  pub planet_color: Color,
+  pub fractal_color: Color,
 }
 
-pub fn saturn_ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    // Define the colors for the stripes
-    let color1 = Color::new(194, 194, 194); // Light tan
-    let color2 = Color::new(128, 127, 122);  // Reddish brown
-    let color3 = Color::new(242, 228, 196); // Pale cream
-
-    // Ring properties
-    let ring_center = Vec3::new(0.0, 0.0, 0.0); // Center of the ring in world space
-    let stripe_width = 0.1;                    // Width of each stripe
-
-    // Compute the radial distance in the XZ plane (ignoring the Y-axis)
-    let fragment_position_xz = fragment.vertex_position.xz(); // Extract x and z
-    let ring_center_xz = ring_center.xz();                   // Extract center x and z
-    let radial_distance = (fragment_position_xz - ring_center_xz).norm(); // Distance in XZ plane
-
-    // Determine which stripe the fragment belongs to
-    let stripe_value = (radial_distance / stripe_width).floor() as i32;
+impl HypnosShader {
+  pub fn from_params(params: &ShaderParams) -> Result<Self, ShaderError> {
+    params.expect_only(&["noise", "planet_color", "fractal_color"])?;
+    Ok(HypnosShader {
+      // Single octave PingPong fractal for the swirling paths
+      noise: params
+        .noise("noise", NoiseConfig::new(NoiseKind::OpenSimplex2s, 3.0).with_fractal(FractalKind::PingPong, 1))?
+        .build(),
+      planet_color: params.color("planet_color", Color::new(0, 40, 0))?,      // Dark green color for the planet
+      fractal_color: params.color("fractal_color", Color::new(255, 255, 0))?, // Yellow for the swirling effect
+    })
+  }
+}
 
-    // Smooth transition between stripes
-    let transition_factor = (radial_distance / stripe_width).fract().abs();
+impl FragmentShader for HypnosShader {
+  fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+    let noise = &self.noise;
 
-    // Determine the current and next stripe colors
-    let current_color = match stripe_value % 3 {
-        0 => color1,
-        1 => color2,
-        _ => color3,
-    };
+    // The paths are fixed to the surface, the planet's spin carries them around
+    let noise_value = noise.get_noise_2d(fragment.vertex_position.x * 5.0, fragment.vertex_position.y * 5.0);
 
-    let next_color = match (stripe_value + 1) % 3 {
-        0 => color1,
-        1 => color2,
-        _ => color3,
-    };
+    // Normalize the noise value to the range [0, 1]
+    let normalized_noise_value = (noise_value + 1.0) * 0.5;
 
-    // Interpolate between the current stripe and the next stripe
-    let base_color = current_color.lerp(&next_color, transition_factor);
-
-    // Adjust brightness based on the fragment's intensity
-    base_color * fragment.intensity.max(0.4)
-}
\ No newline at end of file
+    // Blend the planet color with the fractal pattern based on the noise value
+    self.planet_color.lerp(&self.fractal_color, normalized_noise_value)
+  }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::entity::color::Color;
+use super::noise::NoiseConfig;
+use super::shader::{
+    CraterShader, EarthShader, FragmentShader, HypnosShader, PlutoShader, SaturnRingShader, SaturnShader,
+    SimpleShader, SunShader, VortexShader,
+};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    UnknownShader { name: String, available: Vec<String> },
+    InvalidParameter { key: String, message: String },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::UnknownShader { name, available } => {
+                write!(f, "unknown shader '{}' (available: {})", name, available.join(", "))
+            }
+            ShaderError::InvalidParameter { key, message } => write!(f, "shader parameter '{}': {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Parameters handed to a shader constructor, read from a scene file's `shader_params` table.
+/// Every getter falls back to the shader's built-in value when the key is missing.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderParams {
+    values: toml::Table,
+}
+
+impl ShaderParams {
+    pub fn new(values: toml::Table) -> Self {
+        ShaderParams { values }
+    }
+
+    pub fn float(&self, key: &str, default: f32) -> Result<f32, ShaderError> {
+        match self.values.get(key) {
+            None => Ok(default),
+            Some(toml::Value::Float(value)) => Ok(*value as f32),
+            Some(toml::Value::Integer(value)) => Ok(*value as f32),
+            Some(_) => Err(invalid(key, "expected a number")),
+        }
+    }
+
+    /// A finite number above 0, for sizes the shader divides by.
+    pub fn positive(&self, key: &str, default: f32) -> Result<f32, ShaderError> {
+        let value = self.float(key, default)?;
+        if value.is_finite() && value > 0.0 {
+            Ok(value)
+        } else {
+            Err(invalid(key, "expected a finite number above 0"))
+        }
+    }
+
+    /// A finite number that is 0 or more.
+    pub fn non_negative(&self, key: &str, default: f32) -> Result<f32, ShaderError> {
+        let value = self.float(key, default)?;
+        if value.is_finite() && value >= 0.0 {
+            Ok(value)
+        } else {
+            Err(invalid(key, "expected a finite number of at least 0"))
+        }
+    }
+
+    /// A color written as `[r, g, b]` with channels in 0..=255.
+    pub fn color(&self, key: &str, default: Color) -> Result<Color, ShaderError> {
+        match self.values.get(key) {
+            None => Ok(default),
+            Some(value) => parse_color(key, value),
+        }
+    }
+
+    /// A list of colors written as `[[r, g, b], ...]`, must not be empty.
+    pub fn colors(&self, key: &str, default: &[Color]) -> Result<Vec<Color>, ShaderError> {
+        match self.values.get(key) {
+            None => Ok(default.to_vec()),
+            Some(toml::Value::Array(values)) if !values.is_empty() => {
+                values.iter().map(|value| parse_color(key, value)).collect()
+            }
+            Some(_) => Err(invalid(key, "expected a non-empty list of [r, g, b] colors")),
+        }
+    }
+
+    /// A noise table with the same fields as `NoiseConfig`.
+    pub fn noise(&self, key: &str, default: NoiseConfig) -> Result<NoiseConfig, ShaderError> {
+        match self.values.get(key) {
+            None => Ok(default),
+            Some(value) => value.clone().try_into().map_err(|error| invalid(key, &error.to_string())),
+        }
+    }
+
+    /// Rejects keys the shader doesn't know about, so typos in scene files don't go unnoticed.
+    pub fn expect_only(&self, known: &[&str]) -> Result<(), ShaderError> {
+        match self.values.keys().find(|key| !known.contains(&key.as_str())) {
+            Some(key) => Err(invalid(key, &format!("unknown parameter (expected one of: {})", known.join(", ")))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_color(key: &str, value: &toml::Value) -> Result<Color, ShaderError> {
+    let channels = value
+        .as_array()
+        .filter(|channels| channels.len() == 3)
+        .and_then(|channels| {
+            channels
+                .iter()
+                .map(|channel| channel.as_integer().and_then(|channel| u8::try_from(channel).ok()))
+                .collect::<Option<Vec<u8>>>()
+        });
+
+    match channels {
+        Some(channels) => Ok(Color::new(channels[0], channels[1], channels[2])),
+        None => Err(invalid(key, "expected a color [r, g, b] with channels in 0..=255")),
+    }
+}
+
+fn invalid(key: &str, message: &str) -> ShaderError {
+    ShaderError::InvalidParameter {
+        key: key.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Builds a configured shader, reporting bad parameters.
+pub type ShaderConstructor = fn(&ShaderParams) -> Result<Box<dyn FragmentShader>, ShaderError>;
+
+/// Maps the shader names used in scene files to their constructors.
+pub struct ShaderRegistry {
+    constructors: BTreeMap<String, ShaderConstructor>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry {
+            constructors: BTreeMap::new(),
+        }
+    }
+
+    /// A registry with every shader that ships with the project.
+    pub fn builtin() -> Self {
+        let mut registry = ShaderRegistry::new();
+        registry.register("simple", |params| Ok(Box::new(SimpleShader::from_params(params)?)));
+        registry.register("sun", |params| Ok(Box::new(SunShader::from_params(params)?)));
+        registry.register("earth", |params| Ok(Box::new(EarthShader::from_params(params)?)));
+        registry.register("crater", |params| Ok(Box::new(CraterShader::from_params(params)?)));
+        registry.register("saturn", |params| Ok(Box::new(SaturnShader::from_params(params)?)));
+        registry.register("saturn_ring", |params| Ok(Box::new(SaturnRingShader::from_params(params)?)));
+        registry.register("vortex", |params| Ok(Box::new(VortexShader::from_params(params)?)));
+        registry.register("hypnos", |params| Ok(Box::new(HypnosShader::from_params(params)?)));
+        registry.register("pluto", |params| Ok(Box::new(PlutoShader::from_params(params)?)));
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, constructor: ShaderConstructor) {
+        self.constructors.insert(name.to_string(), constructor);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.constructors.keys().cloned().collect()
+    }
+
+    pub fn create(&self, name: &str, params: &ShaderParams) -> Result<Box<dyn FragmentShader>, ShaderError> {
+        let constructor = self.constructors.get(name).ok_or_else(|| ShaderError::UnknownShader {
+            name: name.to_string(),
+            available: self.names(),
+        })?;
+        constructor(params)
+    }
+}
+
+impl Default for ShaderRegistry {
+    fn default() -> Self {
+        ShaderRegistry::builtin()
+    }
+}
@@ -0,0 +1,164 @@
+use nalgebra_glm::{Vec3, Vec4};
+
+use super::entity::light::{Light, LightKind};
+use super::entity::vertex::Vertex;
+use super::model::Model;
+use super::picking::bounding_radius;
+
+// Share of the light a ring stops, the gaps between its bands let some through
+const RING_OPACITY: f32 = 0.6;
+// A mesh thinner than this share of its width along its flattest axis casts a ring shadow
+const FLATNESS: f32 = 0.1;
+// Points this close to an occluder's surface belong to it and are not shadowed by it
+const SURFACE_MARGIN: f32 = 1.02;
+
+/// A mesh's extent in its own model space, fitted once when the model is built so shadows
+/// and picking only have to move it along with the model every frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshBounds {
+    pub radius: f32,              // Distance of the farthest vertex from the origin
+    pub ring: Option<RingBounds>, // Set when the mesh is flat enough to cast a ring shadow
+}
+
+/// The annulus a flat mesh lies in, `normal` is unit length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RingBounds {
+    pub normal: Vec3,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub thickness: f32,
+}
+
+impl MeshBounds {
+    pub fn fit(vertices: &[Vertex]) -> MeshBounds {
+        let radius = vertices.iter().map(|vertex| vertex.position.norm()).fold(0.0, f32::max);
+
+        // The mesh is a triangle soup, its summed face normals give the plane a flat mesh lies in
+        let mut normal = Vec3::zeros();
+        for triangle in vertices.chunks_exact(3) {
+            let face = (triangle[1].position - triangle[0].position).cross(&(triangle[2].position - triangle[0].position));
+            normal += if face.dot(&normal) < 0.0 { -face } else { face };
+        }
+        if normal.norm() == 0.0 {
+            return MeshBounds { radius, ring: None };
+        }
+
+        let normal = normal.normalize();
+        let heights = vertices.iter().map(|vertex| vertex.position.dot(&normal).abs());
+        let radii = vertices.iter().map(|vertex| (vertex.position - normal * vertex.position.dot(&normal)).norm());
+        let thickness = heights.fold(0.0, f32::max);
+        let (inner_radius, outer_radius) = radii.fold((f32::MAX, 0.0f32), |(min, max), radius| (min.min(radius), max.max(radius)));
+
+        let ring = (thickness < outer_radius * FLATNESS).then_some(RingBounds { normal, inner_radius, outer_radius, thickness });
+        MeshBounds { radius, ring }
+    }
+}
+
+/// Analytic stand-in for a body when testing whether it blocks a light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Occluder {
+    Sphere { center: Vec3, radius: f32 },
+    /// A flat annulus like Saturn's rings, `normal` is unit length.
+    Ring { center: Vec3, normal: Vec3, inner_radius: f32, outer_radius: f32, thickness: f32 },
+}
+
+impl Occluder {
+    /// Places the model's load-time bounds in the world: a sphere, or a ring when its mesh is flat.
+    pub fn from_model(model: &dyn Model) -> Occluder {
+        let center = model.get_position();
+
+        match model.get_bounds().ring {
+            Some(ring) => {
+                let scale = model.get_scale();
+                let normal = model.get_model_matrix() * Vec4::new(ring.normal.x, ring.normal.y, ring.normal.z, 0.0);
+                Occluder::Ring {
+                    center,
+                    normal: normal.xyz().normalize(),
+                    inner_radius: ring.inner_radius * scale,
+                    outer_radius: ring.outer_radius * scale,
+                    thickness: ring.thickness * scale,
+                }
+            }
+            None => Occluder::Sphere { center, radius: bounding_radius(model) },
+        }
+    }
+
+    /// Share of `light` that gets past this occluder to `position`, from 0 (full shadow) to 1.
+    pub fn visibility(&self, position: &Vec3, light: &Light) -> f32 {
+        let light_dir = light.direction_from(position);
+        let (light_distance, light_angle) = light.source_from(position);
+
+        match *self {
+            Occluder::Sphere { center, radius } => {
+                let to_center = center - position;
+                let distance = to_center.norm();
+                // Its own surface, and the glowing body around the light itself
+                if distance <= radius * SURFACE_MARGIN || distance - radius >= light_distance || contains_light(light, center, radius) {
+                    return 1.0;
+                }
+
+                let occluder_angle = (radius / distance).asin();
+                let separation = (to_center.dot(&light_dir) / distance).clamp(-1.0, 1.0).acos();
+                if separation >= occluder_angle + light_angle {
+                    return 1.0;
+                }
+
+                // Fully covered (umbra), or a small body crossing a big light (antumbra)
+                let covered = if occluder_angle >= light_angle {
+                    0.0
+                } else {
+                    1.0 - (occluder_angle / light_angle).powi(2)
+                };
+                let inner = (occluder_angle - light_angle).abs();
+                if separation <= inner {
+                    return covered;
+                }
+
+                // The penumbra fades linearly as the discs slide apart
+                covered + (1.0 - covered) * (separation - inner) / (occluder_angle + light_angle - inner)
+            }
+            Occluder::Ring { center, normal, inner_radius, outer_radius, thickness } => {
+                // Points on the ring itself
+                let height = (position - center).dot(&normal);
+                if height.abs() <= thickness {
+                    return 1.0;
+                }
+
+                let facing = light_dir.dot(&normal);
+                if facing.abs() < f32::EPSILON {
+                    return 1.0;
+                }
+                let along = -height / facing;
+                if along <= 0.0 || along >= light_distance {
+                    return 1.0;
+                }
+
+                let radial = (position + light_dir * along - center).norm();
+                if radial >= inner_radius && radial <= outer_radius {
+                    1.0 - RING_OPACITY
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// Share of `light` reaching `position` past every occluder, for darkening fragments in a body's shadow.
+pub fn light_visibility(occluders: &[Occluder], position: &Vec3, light: &Light) -> f32 {
+    let mut visibility = 1.0;
+    for occluder in occluders {
+        visibility *= occluder.visibility(position, light);
+        if visibility <= 0.0 {
+            return 0.0;
+        }
+    }
+    visibility
+}
+
+fn contains_light(light: &Light, center: Vec3, radius: f32) -> bool {
+    match light.kind {
+        LightKind::Point { position, .. } => (position - center).norm() < radius,
+        LightKind::Directional { .. } => false,
+    }
+}
@@ -0,0 +1,163 @@
+use std::any::Any;
+use std::sync::Arc;
+use nalgebra_glm::{quat_angle_axis, quat_normalize, quat_rotate_vec3, quat_to_mat4, Mat4, Quat, Vec3};
+
+use super::entity::vertex::Vertex;
+use super::model::Model;
+use super::render::create_model_matrix;
+use super::shader::FragmentShader;
+use super::shadow::MeshBounds;
+
+/// What the pilot is asking for this frame, every axis in -1..=1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShipControls {
+    pub thrust: Vec3,        // Local axes: +X right, +Y up, -Z forward
+    pub rotation: Vec3,      // Pitch, yaw and roll around the local X, Y and Z axes
+    pub match_velocity: bool, // Burns to fly along with the nearest body
+}
+
+/// The player controlled ship, moved by thrusters and forces instead of fixed steps.
+pub struct Ship {
+    pub vertex_array: Arc<Vec<Vertex>>,
+    pub shader: Box<dyn FragmentShader>,
+    pub position: Vec3,
+    pub scale: f32,
+    pub orientation: Quat,
+    pub collision_radius: f32,
+    pub bounds: MeshBounds,
+
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3, // Radians per second around the local axes
+    pub mass: f32,
+    pub thrust: f32,            // Force of the thrusters on each axis
+    pub turn_rate: f32,         // Angular acceleration at full stick
+    pub angular_damping: f32,   // How quickly spin dies down without input, 0 keeps spinning forever
+    pub gravity: bool,          // Whether bodies in the gravity simulation pull on the ship
+    pub restitution: f32,       // Share of the impact speed kept when bouncing off a surface
+    pub friction: f32,          // How quickly a landed ship is dragged along with the surface
+}
+
+impl Model for Ship {
+    fn get_vertex_array(&self) -> Arc<Vec<Vertex>> {
+        Arc::clone(&self.vertex_array)
+    }
+
+    fn get_shader(&self) -> &dyn FragmentShader {
+        self.shader.as_ref()
+    }
+
+    fn get_position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Vec3){
+        self.position = position;
+    }
+
+    fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    fn get_colision_radius(&self) -> f32 {
+        self.collision_radius
+    }
+
+    fn get_bounds(&self) -> MeshBounds {
+        self.bounds
+    }
+
+    fn get_model_matrix(&self) -> Mat4 {
+        create_model_matrix(self.position, self.scale, Vec3::zeros()) * quat_to_mat4(&self.orientation)
+    }
+
+    fn get_frame(&self) -> Mat4 {
+        create_model_matrix(self.position, 1.0, Vec3::zeros()) * quat_to_mat4(&self.orientation)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Ship {
+    pub fn new(
+        vertex_array: Arc<Vec<Vertex>>,
+        shader: Box<dyn FragmentShader>,
+        position: Vec3,
+        scale: f32,
+        rotation: Vec3,
+        collision_radius: f32,
+    ) -> Self {
+        Ship {
+            bounds: MeshBounds::fit(&vertex_array),
+            vertex_array,
+            shader,
+            position,
+            scale,
+            orientation: euler_to_quat(rotation),
+            collision_radius,
+            velocity: Vec3::zeros(),
+            angular_velocity: Vec3::zeros(),
+            mass: 1.0,
+            thrust: 20.0,
+            turn_rate: 3.0,
+            angular_damping: 2.0,
+            gravity: false,
+            restitution: 0.4,
+            friction: 3.0,
+        }
+    }
+
+    /// Turns a direction on the ship's local axes into world space.
+    pub fn to_world(&self, direction: &Vec3) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, direction)
+    }
+
+    /// Integrates one step of `delta_time` seconds. `acceleration` holds outside forces such as
+    /// gravity and `target_velocity` is what the match velocity assist tries to reach.
+    pub fn fly(&mut self, controls: &ShipControls, acceleration: Vec3, target_velocity: Vec3, delta_time: f32) {
+        if delta_time <= 0.0 {
+            return;
+        }
+
+        let max_acceleration = self.thrust / self.mass;
+        let mut acceleration = acceleration + self.to_world(&clamp_axes(controls.thrust)) * max_acceleration;
+
+        if controls.match_velocity {
+            // Burn against the relative velocity, without overshooting in a single step
+            let relative = self.velocity - target_velocity;
+            let needed = relative.norm() / delta_time;
+            if needed > 0.0 {
+                acceleration -= relative.normalize() * needed.min(max_acceleration);
+            }
+        }
+
+        // Semi-implicit Euler: update velocity first so orbits don't slowly gain energy
+        self.velocity += acceleration * delta_time;
+        self.position += self.velocity * delta_time;
+
+        self.angular_velocity += clamp_axes(controls.rotation) * (self.turn_rate * delta_time);
+        self.angular_velocity *= (-self.angular_damping * delta_time).exp();
+
+        let angle = self.angular_velocity.norm() * delta_time;
+        if angle > 0.0 {
+            // Local angular velocity, so it's applied on the right of the orientation
+            let spin = quat_angle_axis(angle, &self.angular_velocity.normalize());
+            self.orientation = quat_normalize(&(self.orientation * spin));
+        }
+    }
+}
+
+fn clamp_axes(input: Vec3) -> Vec3 {
+    input.map(|axis| axis.clamp(-1.0, 1.0))
+}
+
+fn euler_to_quat(rotation: Vec3) -> Quat {
+    quat_angle_axis(rotation.z, &Vec3::z())
+        * quat_angle_axis(rotation.y, &Vec3::y())
+        * quat_angle_axis(rotation.x, &Vec3::x())
+}
@@ -0,0 +1,170 @@
+use nalgebra_glm::Vec3;
+
+/// Where the current loop stands, handed to shaders through `Uniforms`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoopState {
+    pub expansion: f32,        // 0 to 1 while the sun swells into a red giant
+    pub collapse: f32,         // 0 to 1 while it shrinks right before exploding
+    pub supernova: f32,        // 0 to 1 from the explosion until the loop resets
+    pub shockwave_center: Vec3,
+    pub shockwave_radius: f32, // 0 while there is no shockwave
+}
+
+/// The repeating loop: the sun grows old, collapses and goes supernova, its
+/// shockwave sweeps the system and everything starts over.
+/// Times are seconds since the start of the loop.
+#[derive(Clone, Debug)]
+pub struct TimeLoop {
+    pub length: f32,
+    pub expansion_start: f32,
+    pub collapse_start: f32,
+    pub supernova_start: f32,
+    pub giant_scale: f32,     // Sun size at the end of the expansion, relative to its scene scale
+    pub collapsed_scale: f32, // Sun size right before the explosion
+    pub shockwave_speed: f32, // World units per second
+    pub sun: usize,           // Index into `Scene::models`
+    pub sun_scale: f32,       // Scale the sun was given in the scene file
+    pub count: u32,           // Loops completed so far
+    elapsed: f32,
+}
+
+impl TimeLoop {
+    pub fn new(sun: usize, sun_scale: f32) -> Self {
+        TimeLoop {
+            length: 22.0 * 60.0,
+            expansion_start: 19.0 * 60.0,
+            collapse_start: 21.5 * 60.0,
+            supernova_start: 21.6 * 60.0,
+            giant_scale: 1.8,
+            collapsed_scale: 0.2,
+            shockwave_speed: 15.0,
+            sun,
+            sun_scale,
+            count: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// How much of `delta_time` the loop can take. Rewinding stops at the start of the loop,
+    /// there is no going back into the previous one.
+    pub fn clamp_step(&self, delta_time: f32) -> f32 {
+        delta_time.max(-self.elapsed)
+    }
+
+    /// Moves the loop by `delta_time`, returns true when it ran out and the scene has to reset.
+    /// Rewinding steps must go through `clamp_step` first.
+    pub fn advance(&mut self, delta_time: f32) -> bool {
+        self.elapsed += delta_time;
+        if self.elapsed >= self.length {
+            self.elapsed = 0.0;
+            self.count += 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn state(&self, sun_position: Vec3) -> LoopState {
+        let expansion = progress(self.elapsed, self.expansion_start, self.collapse_start);
+        let collapse = progress(self.elapsed, self.collapse_start, self.supernova_start);
+        let supernova = progress(self.elapsed, self.supernova_start, self.length);
+
+        let shockwave_radius = if self.elapsed > self.supernova_start {
+            (self.elapsed - self.supernova_start) * self.shockwave_speed
+        } else {
+            0.0
+        };
+
+        LoopState {
+            expansion,
+            collapse,
+            supernova,
+            shockwave_center: sun_position,
+            shockwave_radius,
+        }
+    }
+
+    /// The sun's scale at the current point of the loop.
+    pub fn sun_scale(&self) -> f32 {
+        let state = self.state(Vec3::zeros());
+
+        let size = if state.supernova > 0.0 {
+            // The remnant blows outwards fast, then keeps growing slowly
+            lerp(self.collapsed_scale, self.giant_scale * 2.0, state.supernova.sqrt())
+        } else if state.collapse > 0.0 {
+            lerp(self.giant_scale, self.collapsed_scale, state.collapse * state.collapse)
+        } else {
+            lerp(1.0, self.giant_scale, smoothstep(state.expansion))
+        };
+
+        self.sun_scale * size
+    }
+}
+
+fn progress(time: f32, start: f32, end: f32) -> f32 {
+    if end <= start {
+        return if time >= end { 1.0 } else { 0.0 };
+    }
+    ((time - start) / (end - start)).clamp(0.0, 1.0)
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_resets_when_the_loop_runs_out() {
+        let mut time_loop = TimeLoop::new(0, 1.0);
+        assert!(!time_loop.advance(time_loop.length - 1.0));
+        assert_eq!(time_loop.count, 0);
+        assert!(time_loop.advance(1.0));
+        assert_eq!(time_loop.elapsed(), 0.0);
+        assert_eq!(time_loop.count, 1);
+    }
+
+    #[test]
+    fn rewinding_stops_at_the_loop_start() {
+        let mut time_loop = TimeLoop::new(0, 1.0);
+        time_loop.advance(0.25);
+        let step = time_loop.clamp_step(-1.0);
+        assert_eq!(step, -0.25);
+        assert!(!time_loop.advance(step));
+        assert_eq!(time_loop.elapsed(), 0.0);
+        assert_eq!(time_loop.clamp_step(-1.0), 0.0);
+    }
+
+    #[test]
+    fn clamp_step_leaves_forward_steps_alone() {
+        let time_loop = TimeLoop::new(0, 1.0);
+        assert_eq!(time_loop.clamp_step(2.0), 2.0);
+    }
+
+    #[test]
+    fn sun_grows_collapses_and_explodes() {
+        let mut time_loop = TimeLoop::new(0, 2.0);
+        assert_eq!(time_loop.sun_scale(), 2.0);
+
+        time_loop.advance(time_loop.collapse_start);
+        assert!((time_loop.sun_scale() - 2.0 * time_loop.giant_scale).abs() < 1e-4);
+
+        time_loop.advance(time_loop.supernova_start - time_loop.collapse_start);
+        assert!((time_loop.sun_scale() - 2.0 * time_loop.collapsed_scale).abs() < 1e-4);
+        assert_eq!(time_loop.state(Vec3::zeros()).shockwave_radius, 0.0);
+
+        time_loop.advance(10.0);
+        let state = time_loop.state(Vec3::zeros());
+        assert!(state.supernova > 0.0);
+        assert!((state.shockwave_radius - 10.0 * time_loop.shockwave_speed).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::str::FromStr;
+
+use nalgebra_glm::Vec3;
+
+/// How HDR light above 1 is squeezed into the displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Clips every channel at 1, like the old 8-bit pipeline.
+    None,
+    /// x / (1 + x), soft and a little flat, keeps the dark side of bodies readable.
+    #[default]
+    Reinhard,
+    /// Filmic curve with a toe and a shoulder, punchier midtones but crushes the shadows.
+    Aces,
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: &Vec3) -> Vec3 {
+        match self {
+            ToneMapping::None => color.map(|channel| channel.clamp(0.0, 1.0)),
+            ToneMapping::Reinhard => color.map(|channel| channel / (1.0 + channel)),
+            ToneMapping::Aces => color.map(aces),
+        }
+    }
+
+    /// The operator after this one, for cycling through them at runtime.
+    pub fn next(&self) -> ToneMapping {
+        match self {
+            ToneMapping::None => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::None,
+        }
+    }
+}
+
+// Narkowicz's fit of the ACES reference curve
+fn aces(x: f32) -> f32 {
+    let x = x.max(0.0);
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+impl fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ToneMapping::None => write!(f, "none"),
+            ToneMapping::Reinhard => write!(f, "reinhard"),
+            ToneMapping::Aces => write!(f, "aces"),
+        }
+    }
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "none" => Ok(ToneMapping::None),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            _ => Err(format!("unknown tone mapping '{}', expected none, reinhard or aces", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMapping; 3] = [ToneMapping::None, ToneMapping::Reinhard, ToneMapping::Aces];
+
+    #[test]
+    fn black_stays_black_and_everything_fits_the_display() {
+        for operator in OPERATORS {
+            assert_eq!(operator.apply(&Vec3::zeros()), Vec3::zeros());
+            for light in [0.1, 1.0, 10.0, 1000.0] {
+                let mapped = operator.apply(&Vec3::repeat(light));
+                assert!(mapped.iter().all(|channel| (0.0..=1.0).contains(channel)), "{} at {}", operator, light);
+            }
+        }
+    }
+
+    #[test]
+    fn brighter_light_never_maps_darker() {
+        for operator in OPERATORS {
+            let mut previous = 0.0;
+            for step in 0..200 {
+                let mapped = operator.apply(&Vec3::repeat(step as f32 * 0.05)).x;
+                assert!(mapped >= previous, "{} at {}", operator, step);
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn operators_match_their_curves() {
+        assert_eq!(ToneMapping::None.apply(&Vec3::new(0.5, 2.0, -1.0)), Vec3::new(0.5, 1.0, 0.0));
+        assert_eq!(ToneMapping::Reinhard.apply(&Vec3::repeat(1.0)), Vec3::repeat(0.5));
+        assert!((ToneMapping::Aces.apply(&Vec3::repeat(100.0)).x - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn names_round_trip_and_cycle_through_every_operator() {
+        for operator in OPERATORS {
+            assert_eq!(operator.to_string().parse::<ToneMapping>(), Ok(operator));
+        }
+        assert!("filmic".parse::<ToneMapping>().is_err());
+        assert_eq!(ToneMapping::None.next().next().next(), ToneMapping::None);
+    }
+}
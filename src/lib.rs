@@ -1,27 +1,48 @@
 mod internal;
 
-use internal::camera::Camera;
-use internal::entity::skybox::Skybox;
-use internal::entity::vertex::{self, Vertex};
-use internal::object::Obj;
-use minifb::{Window, WindowOptions, Key};
+use internal::camera::{Camera, CameraMode};
+use internal::camera_path::{CameraPath, CameraPathPlayer};
+use minifb::{Window, WindowOptions, Key, KeyRepeat, MouseButton, MouseMode};
 use nalgebra_glm::{Mat4, Vec3};
 
-use std::sync::Arc;
-use std::time::Duration;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use std::f32::consts::PI;
 
-use internal::framebuffer::Framebuffer;
-use internal::render::{create_model_matrix, create_perspective_matrix, create_view_matrix, create_viewport_matrix, draw_orbit, render, Uniforms};
+use internal::framebuffer::{Framebuffer, RenderableToFile};
+use internal::render::{create_perspective_matrix, create_view_matrix, create_viewport_matrix, draw_orbit, render, render_atmosphere, Uniforms};
+
+pub use internal::render::{RasterMode, RenderOptions};
+pub use internal::tone_mapping::ToneMapping;
 use internal::entity::color::Color;
-use internal::model::{Model, SimpleModel, Planet};
-use internal::shader::{crater_shader, earth_shader, hypnos_shader, pluto_shader, saturn_ring_shader, saturn_shader, simple_shader, sun_shader, vortex_shader};
+use internal::atmosphere::Atmosphere;
+use internal::model::Planet;
+use internal::scene::Scene;
+use internal::ship::{Ship, ShipControls};
+use internal::collision::CollisionEvent;
+use internal::picking::{bounding_radius, pick, Ray};
+use internal::post_process::PostProcessing;
+use internal::clock::SimulationClock;
+
 
+const WINDOW_WIDTH: usize = 800;
+const WINDOW_HEIGHT: usize = 600;
+const SIMULATION_STEP: f32 = 1.0 / 60.0; // Seconds
+const MAX_FRAME_TIME: f32 = 0.25; // Seconds, longer stalls are dropped instead of simulated
+const DEFAULT_CAMERA_PATH: &str = "./camera_path.toml"; // Where recorded keyframes go without --camera-path
+
+/// Opens the interactive window. `camera_path` is a keyframe file to play back with `F`,
+/// keyframes recorded with `G` are saved back to it with `H`.
+/// Tone mapping and exposure start from `options` and can be changed with `T`, `[` and `]`,
+/// `1`-`9` toggle the scene's post-processing passes and `0` all of them.
+pub fn start(scene_path: &Path, options: &RenderOptions, camera_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let mut options = options.clone();
 
-pub fn start() {
     // Window Size configuration
-    let window_width = 800;
-    let window_height = 600;
+    let window_width = WINDOW_WIDTH;
+    let window_height = WINDOW_HEIGHT;
     let framebuffer_width =  window_width;
     let framebuffer_height = window_height;
     
@@ -29,193 +50,63 @@ pub fn start() {
     let frame_delay = Duration::from_millis(16);
   
     // Window Objects initialization
-    let mut framebuffer = Framebuffer::new(window_width, window_height, Color::new(0, 0, 0));
+    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height, Color::new(0, 0, 0));
+    let mut scene = Scene::load(scene_path)?;
+
+    let path_file = camera_path.unwrap_or(Path::new(DEFAULT_CAMERA_PATH));
+    let mut player = CameraPathPlayer::new(match camera_path {
+        Some(path) => CameraPath::load(path)?,
+        None => CameraPath::default(),
+    });
+
     let mut window = Window::new(
       "Minecraft Diorama",
       window_width,
       window_height,
       WindowOptions::default()
     ).unwrap();
-    
-    let skybox = Skybox::new(200, 200.0, Color::new(255, 255, 255), Color::new(0, 0, 20));
-
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 10.0, 60.0),
-        Vec3::new(0.0, 0.0, -1.0), 
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        15.0,
-        Vec3::new(-5.0, 110.0, -10.0), 
-        Vec3::new(0.0, 0.0, 0.0),
-    );
-    
-    let space_ship_obj = Obj::load("./assets/mesh/spaceShip2.obj").expect("Failed to load obj");
-    let planet_obj = Obj::load("./assets/mesh/sphere.obj").expect("Failed to load obj");
-    let rings_obj = Obj::load("./assets/mesh/rings.obj").expect("Failed to load obj");
-    
-    let space_ship_vertices = Arc::new(space_ship_obj.get_vertex_array());
-    let planet_vertices = Arc::new(planet_obj.get_vertex_array());
-    let rings_vertices = Arc::new(rings_obj.get_vertex_array());
-    
-    // Create a list of models with one inline-defined SimpleModel
-    let mut models: Vec<Box<dyn Model>> = vec![
-        Box::new(SimpleModel {
-            vertex_array: space_ship_vertices.clone(), // Clone the Arc
-            shader: simple_shader,
-            position: Vec3::new(0.0, 10.0, 55.0),
-            scale: 1.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            collision_radius: 5.0,
-        }),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            10.0,
-            sun_shader,
-            1.0,
-            0.0,
-            0.0,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            0
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            1.0,
-            crater_shader,
-            15.0,
-            0.0,
-            0.0008,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            2.0,
-            earth_shader,
-            25.0,
-            0.0,
-            0.0004,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            2.0,
-            saturn_shader,
-            30.0,
-            0.0,
-            0.0001,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            rings_vertices.clone(), // Clone the Arc
-            2.0,
-            saturn_ring_shader,
-            30.0,
-            0.0,
-            0.0001,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            4.0,
-            vortex_shader,
-            38.0,
-            0.0,
-            0.0002,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            4.0,
-            hypnos_shader,
-            45.0,
-            0.0,
-            0.0001,
-            3.0,
-            Vec3::new(0.0, 0.0, 0.0),
-            40
-        )),
-        Box::new(Planet::new(
-            planet_vertices.clone(), // Clone the Arc
-            4.0,
-            pluto_shader,
-            60.0,
-            0.0,
-            0.00015,
-            3.0,
-            Vec3::new(10.0, 0.0, 5.0),
-            40
-        )),
-    ];
-    // let vertex_array = obj.get_vertex_array();
-    // let vertex_array : Vec<Vertex> = vec![];
-
-    let mut view_matrix : Mat4;
-    let perspective_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
+
     let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
     
-    let mut time : f32 = 0.0;
+    let mut clock = SimulationClock::new(SIMULATION_STEP);
+    let mut last_frame = Instant::now();
+    let mut was_clicking = false;
     
     // RENDER LOOP
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
-        
-        time += 0.1;
 
-        let subject = models.get_mut(0).expect("Subject not found."); // OR SOMETHING LIKE THAT
+        let now = Instant::now();
+        let real_delta = (now - last_frame).as_secs_f32().min(MAX_FRAME_TIME);
+        last_frame = now;
 
-        handle_input(&window, &mut camera, &mut **subject); // MODIFY THE CAMERA AND SUBJECT POSITION
-        
-        framebuffer.clear();
-        framebuffer.set_current_color(Color::new(255, 255, 255));
-
-        view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
+        let controls = handle_input(&window, &mut scene.camera, &mut clock); // MODIFY THE CAMERA, CLOCK AND READ THE SHIP CONTROLS
+        handle_path_input(&window, &mut player, &mut scene.camera, path_file);
+        handle_display_input(&window, &mut options, &mut scene.post_processing);
 
-        skybox.render(&mut framebuffer, &perspective_matrix, &view_matrix);
-        
-        /* 
-            draw_orbit(&mut framebuffer, 
-                Vec3::new(0.0, 0.0, 0.0),
-                15.0, 
-                &perspective_matrix, 
-                &view_matrix, 
-                &viewport_matrix,
-                20, 
-                Color::new(255, 200, 255)
-            );
-        */
-
-        for model in &mut models{
-            
-            let model_matrix = create_model_matrix(model.get_position(), model.get_scale(), model.get_rotation());
-
-            let uniforms = Uniforms{ model_matrix , view_matrix, perspective_matrix, viewport_matrix, time};
-            
-            if let Some(planet) = model.as_any_mut().downcast_mut::<Planet>() {
-                draw_orbit(
-                    &mut framebuffer,
-                    &uniforms,
-                    &planet.orbit_segments,
-                    &camera,
-                    Color::new(255, 255, 255)
-                );
-                planet.translate(time);
+        // Clicking a body makes the orbit camera follow it
+        let clicking = window.get_mouse_down(MouseButton::Left);
+        if clicking && !was_clicking {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                focus_at(&mut scene, x, y, window_width as f32, window_height as f32);
             }
-            
-            render(&mut framebuffer, &uniforms, model.get_vertex_array(), &camera, model.get_shader());
         }
+        was_clicking = clicking;
+        
+        let focus_position = scene.models[scene.camera.focus].get_position();
+        let loop_count = scene.time_loop.as_ref().map(|time_loop| time_loop.count);
+        simulate(&mut scene, &mut clock, &controls, real_delta);
+        // A new loop puts the camera back where it started, don't drag it to the focus
+        if loop_count == scene.time_loop.as_ref().map(|time_loop| time_loop.count) {
+            follow_focus(&mut scene, focus_position, real_delta);
+        }
+        chase_ship(&mut scene, real_delta);
+        player.update(&mut scene.camera, real_delta);
+        render_frame(&mut framebuffer, &scene, &options, &viewport_matrix, clock.time());
 
+        window.set_title(&window_title(&scene, &clock, &options));
 
         window
          .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
@@ -224,98 +115,405 @@ pub fn start() {
 
         std::thread::sleep(frame_delay)
     }
+
+    Ok(())
+}
+
+/// Renders `frames` frames of the scene without opening a window, advancing the
+/// simulation by `time_step` per frame and writing each one as a BMP into `output_dir`.
+/// With a `camera_path` the camera flies along it from the first frame, `time_step` seconds per frame.
+pub fn start_headless(
+    scene_path: &Path,
+    options: &RenderOptions,
+    frames: usize,
+    time_step: f32,
+    output_dir: &Path,
+    camera_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let framebuffer_width = WINDOW_WIDTH;
+    let framebuffer_height = WINDOW_HEIGHT;
+
+    fs::create_dir_all(output_dir)?;
+
+    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height, Color::new(0, 0, 0));
+    let mut scene = Scene::load(scene_path)?;
+
+    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
+
+    let mut clock = SimulationClock::new(SIMULATION_STEP);
+
+    let mut player = None;
+    if let Some(path) = camera_path {
+        let mut path_player = CameraPathPlayer::new(CameraPath::load(path)?);
+        path_player.play(&mut scene.camera, true);
+        player = Some(path_player);
+    }
+
+    for frame in 0..frames {
+        let focus_position = scene.models[scene.camera.focus].get_position();
+        // Feed the clock in window-sized slices so long steps aren't cut short by its stall protection
+        let mut remaining = time_step;
+        while remaining > 0.0 {
+            let slice = remaining.min(MAX_FRAME_TIME);
+            simulate(&mut scene, &mut clock, &ShipControls::default(), slice);
+            remaining -= slice;
+        }
+        follow_focus(&mut scene, focus_position, time_step);
+        chase_ship(&mut scene, time_step);
+        render_frame(&mut framebuffer, &scene, options, &viewport_matrix, clock.time());
+        // The first frame shows the first keyframe, so the path moves on after rendering
+        if let Some(player) = &mut player {
+            player.update(&mut scene.camera, time_step);
+        }
+
+        let filename = output_dir.join(format!("frame_{:05}.bmp", frame));
+        framebuffer.render_buffer(&filename.to_string_lossy())?;
+        match &scene.gravity {
+            Some(gravity) => println!("Rendered {} (energy drift {:+.4}%)", filename.display(), gravity.energy_drift() * 100.0),
+            None => println!("Rendered {}", filename.display()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs every fixed step the clock hands out for `real_delta` seconds of wall clock time.
+fn simulate(scene: &mut Scene, clock: &mut SimulationClock, controls: &ShipControls, real_delta: f32) {
+    for _ in 0..clock.advance(real_delta) {
+        let step = clock.step_delta();
+        let delta_time = scene.clamp_step(step);
+        clock.refund(step - delta_time);
+        scene.update(delta_time);
+        scene.fly_ship(controls, delta_time);
+        let collisions = scene.collide(delta_time);
+        report_collisions(scene, &collisions);
+    }
+}
+
+fn window_title(scene: &Scene, clock: &SimulationClock, options: &RenderOptions) -> String {
+    let mut title = format!("Minecraft Diorama - t {:.1}s x{}", clock.time(), clock.time_scale);
+    if clock.paused {
+        title.push_str(" (paused)");
+    }
+    title.push_str(&format!(" - {} camera", scene.camera.mode.name()));
+    if scene.camera.mode == CameraMode::Orbit {
+        title.push_str(&format!(" on {}", scene.model_name(scene.camera.focus)));
+    }
+    if let Some(time_loop) = &scene.time_loop {
+        let elapsed = time_loop.elapsed() as u32;
+        title.push_str(&format!(" - loop {} at {:02}:{:02}", time_loop.count + 1, elapsed / 60, elapsed % 60));
+    }
+    if let Some(gravity) = &scene.gravity {
+        title.push_str(&format!(" - energy drift {:+.4}%", gravity.energy_drift() * 100.0));
+    }
+    title.push_str(&format!(" - {} x{:.2}", options.tone_mapping, options.exposure));
+    let passes: Vec<&str> = scene.post_processing.passes.iter()
+        .filter(|pass| options.post_processing && pass.enabled)
+        .map(|pass| pass.effect.name())
+        .collect();
+    if !passes.is_empty() {
+        title.push_str(&format!(" + {}", passes.join(", ")));
+    }
+    title
+}
+
+/// Draws one frame of the scene into the framebuffer.
+fn render_frame(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    options: &RenderOptions,
+    viewport_matrix: &Mat4,
+    time: f32,
+) {
+    framebuffer.clear();
+    framebuffer.set_current_color(Color::new(255, 255, 255));
+
+    let view = scene.camera.view();
+    let view_matrix = create_view_matrix(view.eye, view.center, view.up);
+    let perspective_matrix = create_perspective_matrix(framebuffer.width as f32, framebuffer.height as f32, scene.camera.field_of_view());
+
+    scene.skybox.render(framebuffer, &perspective_matrix, &view_matrix);
+
+    let occluders = scene.occluders();
+    let uniforms_for = |model_matrix: Mat4| Uniforms{
+        model_matrix,
+        view_matrix,
+        perspective_matrix,
+        viewport_matrix: *viewport_matrix,
+        time,
+        camera_position: view.eye,
+        lights: &scene.lights,
+        occluders: &occluders,
+        time_loop: scene.loop_state(),
+    };
+
+    // Opaque pass, in scene order. Translucent models and atmospheres wait for the transparent pass
+    let mut transparent: Vec<(f32, usize, Option<&Atmosphere>)> = Vec::new(); // (distance to the eye, model index, its atmosphere shell)
+    for (index, model) in scene.models.iter().enumerate() {
+        let uniforms = uniforms_for(model.get_model_matrix());
+        let distance = (model.get_position() - view.eye).norm();
+
+        if let Some(planet) = model.as_any().downcast_ref::<Planet>() {
+            draw_orbit(
+                framebuffer,
+                &uniforms,
+                &planet.orbit_segments,
+                &planet.parent_frame,
+                &scene.camera,
+                Color::new(255, 255, 255)
+            );
+        }
+
+        let material = model.get_material();
+        if material.is_transparent() {
+            transparent.push((distance, index, None));
+        } else {
+            render(framebuffer, &uniforms, model.get_vertex_array(), model.get_shader(), &material, options.raster_mode);
+        }
+
+        // Pushed after its body, so the body is drawn first when a translucent planet has one
+        if let Some(atmosphere) = model.as_any().downcast_ref::<Planet>().and_then(|planet| planet.atmosphere.as_ref()) {
+            transparent.push((distance, index, Some(atmosphere)));
+        }
+    }
+
+    // Transparent pass, furthest first so nearer layers blend over the ones behind them.
+    // The sort is stable, so ties keep the order they were pushed in
+    transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+    for (_, index, atmosphere) in transparent {
+        let model = &scene.models[index];
+        match atmosphere {
+            Some(atmosphere) => {
+                let shell = model.get_model_matrix() * nalgebra_glm::scaling(&Vec3::repeat(atmosphere.radius / model.get_scale()));
+                render_atmosphere(framebuffer, &uniforms_for(shell), model.get_vertex_array(), atmosphere, options.raster_mode);
+            }
+            None => {
+                let uniforms = uniforms_for(model.get_model_matrix());
+                render(framebuffer, &uniforms, model.get_vertex_array(), model.get_shader(), &model.get_material(), options.raster_mode);
+            }
+        }
+    }
+
+    // Post-processing works on the HDR frame, so bloom picks up light brighter than white
+    if options.post_processing {
+        scene.post_processing.apply(framebuffer, time.to_bits());
+    }
+    framebuffer.resolve(options.exposure, options.tone_mapping);
 }
 
-fn handle_input(window: &Window, camera: &mut Camera, subject: &mut dyn Model) {
+fn handle_input(window: &Window, camera: &mut Camera, clock: &mut SimulationClock) -> ShipControls {
 
     const ROTATION_SPEED : f32 = PI /20.0;
+    const ROLL_SPEED : f32 = PI / 60.0;
     const ZOOM_SPEED : f32 = 1.0;
-    const TRANSLATE_STEP : f32 = 0.3;
-    const LIGHT_ROTATION: f32 = PI / 20.0; // Light rotation angle for subject
+    const FLY_SPEED : f32 = 0.5;
+    const FLY_TURN_SPEED : f32 = PI / 90.0;
+    const MAX_TIME_SCALE : f32 = 64.0;
 
-    // camera orbit controls
-    if window.is_key_down(Key::B) {
-        camera.toogle_bird_view();
+    // simulation clock controls
+    if window.is_key_pressed(Key::P, KeyRepeat::No) {
+        clock.toggle_pause();
     }
-
-    // camera orbit controls
-    if window.is_key_down(Key::D) {
-        camera.orbit(-ROTATION_SPEED, 0.0);
+    if window.is_key_pressed(Key::N, KeyRepeat::Yes) {
+        clock.request_step();
     }
-    if window.is_key_down(Key::A) {
-        camera.orbit(ROTATION_SPEED, 0.0);
+    if window.is_key_pressed(Key::R, KeyRepeat::No) {
+        clock.time_scale = -clock.time_scale;
     }
-    if window.is_key_down(Key::S) {
-        camera.orbit(0.0, -ROTATION_SPEED);
+    if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+        clock.time_scale = (clock.time_scale * 2.0).clamp(-MAX_TIME_SCALE, MAX_TIME_SCALE);
     }
-    if window.is_key_down(Key::W) {
-        camera.orbit(0.0, ROTATION_SPEED);
+    if window.is_key_pressed(Key::Minus, KeyRepeat::No) && clock.time_scale.abs() > 1.0 / MAX_TIME_SCALE {
+        clock.time_scale /= 2.0;
     }
 
-    // camera zoom
-    if window.is_key_down(Key::Q) {
-        camera.zoom(ZOOM_SPEED);
+    // Each axis is +1 / -1 while one of its two keys is held
+    let axis = |positive: Key, negative: Key| {
+        window.is_key_down(positive) as i32 as f32 - window.is_key_down(negative) as i32 as f32
+    };
+
+    if window.is_key_pressed(Key::V, KeyRepeat::No) {
+        camera.next_mode();
     }
-    if window.is_key_down(Key::E) {
-        camera.zoom(-ZOOM_SPEED);
+
+    match camera.mode {
+        CameraMode::Orbit => {
+            // camera orbit controls
+            if window.is_key_down(Key::B) {
+                camera.toogle_bird_view();
+            }
+
+            // camera orbit controls
+            if window.is_key_down(Key::D) {
+                camera.orbit(-ROTATION_SPEED, 0.0);
+            }
+            if window.is_key_down(Key::A) {
+                camera.orbit(ROTATION_SPEED, 0.0);
+            }
+            if window.is_key_down(Key::S) {
+                camera.orbit(0.0, -ROTATION_SPEED);
+            }
+            if window.is_key_down(Key::W) {
+                camera.orbit(0.0, ROTATION_SPEED);
+            }
+
+            if window.is_key_down(Key::Comma) {
+                camera.roll(ROLL_SPEED);
+            }
+            if window.is_key_down(Key::Period) {
+                camera.roll(-ROLL_SPEED);
+            }
+
+            // camera zoom
+            if window.is_key_down(Key::Q) {
+                camera.zoom(ZOOM_SPEED);
+            }
+            if window.is_key_down(Key::E) {
+                camera.zoom(-ZOOM_SPEED);
+            }
+
+            if camera.is_bird_view{
+                return ShipControls::default();
+            }
+        }
+        CameraMode::FreeFly => {
+            // The free camera takes over the ship's turning keys, so the ship drifts meanwhile
+            camera.fly(
+                Vec3::new(
+                    axis(Key::D, Key::A), // Right / left
+                    axis(Key::E, Key::Q), // Up / down
+                    axis(Key::S, Key::W), // Back / forward
+                ) * FLY_SPEED,
+                Vec3::new(
+                    axis(Key::Up, Key::Down),    // Pitch
+                    axis(Key::Left, Key::Right), // Yaw
+                    axis(Key::Z, Key::C),        // Roll
+                ) * FLY_TURN_SPEED,
+            );
+            return ShipControls::default();
+        }
+        CameraMode::Path => {}
+        CameraMode::Chase => {
+            // chase distance
+            if window.is_key_down(Key::Q) {
+                camera.zoom(ZOOM_SPEED);
+            }
+            if window.is_key_down(Key::E) {
+                camera.zoom(-ZOOM_SPEED);
+            }
+        }
     }
 
-    if camera.is_bird_view{
-        return
+    // Ship thrusters along its local axes
+    ShipControls {
+        thrust: Vec3::new(
+            axis(Key::L, Key::J), // Right / left
+            axis(Key::O, Key::U), // Up / down
+            axis(Key::K, Key::I), // Back / forward
+        ),
+        rotation: Vec3::new(
+            axis(Key::Up, Key::Down),    // Pitch
+            axis(Key::Left, Key::Right), // Yaw
+            axis(Key::Z, Key::C),        // Roll
+        ),
+        match_velocity: window.is_key_down(Key::M),
     }
+}
+
+/// Display keys: cycle the tone mapping operator, step the exposure down or up by a third of a stop
+/// and switch post-processing passes on and off.
+fn handle_display_input(window: &Window, options: &mut RenderOptions, post_processing: &mut PostProcessing) {
+    const PASS_KEYS: [Key; 9] = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9];
 
-    let mut subject_position = subject.get_position();
-    let mut subject_rotation = subject.get_rotation(); // Assuming `get_rotation` returns the current rotation
+    const EXPOSURE_STEP: f32 = 1.259_921; // 2^(1/3)
 
-    // Model controls with rotation
-    if window.is_key_down(Key::J) { // Move left
-        subject_position.x -= TRANSLATE_STEP;
-        subject_rotation.z = LIGHT_ROTATION; // Rotate counter-clockwise around Y-axis
-        subject.set_position(subject_position);
-        subject.set_rotation(subject_rotation);
-        camera.change_center(subject_position);
+    if window.is_key_pressed(Key::T, KeyRepeat::No) {
+        options.tone_mapping = options.tone_mapping.next();
     }
-    if window.is_key_down(Key::L) { // Move right
-        subject_position.x += TRANSLATE_STEP;
-        subject_rotation.z = -LIGHT_ROTATION; // Rotate clockwise around Y-axis
-        subject.set_position(subject_position);
-        subject.set_rotation(subject_rotation);
-        camera.change_center(subject_position);
+    if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+        options.exposure /= EXPOSURE_STEP;
     }
-    if window.is_key_down(Key::K) { // Move back
-        subject_position.z += TRANSLATE_STEP;
-        subject_rotation.x = LIGHT_ROTATION; // Rotate upwards around X-axis
-        subject.set_position(subject_position);
-        subject.set_rotation(subject_rotation);
-        camera.change_center(subject_position);
+    if window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+        options.exposure *= EXPOSURE_STEP;
     }
-    if window.is_key_down(Key::I) { // Move forward
-        subject_position.z -= TRANSLATE_STEP;
-        subject_rotation.x = -LIGHT_ROTATION; // Rotate downwards around X-axis
-        subject.set_position(subject_position);
-        subject.set_rotation(subject_rotation);
-        camera.change_center(subject_position);
+
+    if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
+        options.post_processing = !options.post_processing;
+    }
+    for (index, key) in PASS_KEYS.iter().enumerate() {
+        if window.is_key_pressed(*key, KeyRepeat::No) {
+            post_processing.toggle(index);
+        }
     }
+}
 
-    if window.is_key_down(Key::U) {
-        subject_position.y -= TRANSLATE_STEP;
-        subject.set_position(subject_position);
-        camera.change_center(subject_position);
+/// Camera path keys: play or stop the loaded path, record the current view as a keyframe and save them.
+fn handle_path_input(window: &Window, player: &mut CameraPathPlayer, camera: &mut Camera, path_file: &Path) {
+    if window.is_key_pressed(Key::F, KeyRepeat::No) {
+        if player.playing {
+            player.stop(camera);
+        } else {
+            player.play(camera, false);
+        }
+    }
+    if window.is_key_pressed(Key::G, KeyRepeat::No) {
+        player.path.record(camera);
+        println!("Recorded camera keyframe {} at {:.1}s", player.path.keyframes.len(), player.path.duration());
     }
-    if window.is_key_down(Key::O) {
-        subject_position.y += TRANSLATE_STEP;
-        subject.set_position(subject_position);
-        camera.change_center(subject_position);
+    if window.is_key_pressed(Key::H, KeyRepeat::No) {
+        match player.path.save(path_file) {
+            Ok(()) => println!("Saved {} camera keyframes to {}", player.path.keyframes.len(), path_file.display()),
+            Err(error) => eprintln!("Could not save the camera path: {}", error),
+        }
     }
+}
+
+fn report_collisions(scene: &Scene, events: &[CollisionEvent]) {
+    for event in events.iter().filter(|event| event.bounced) {
+        println!("The {} bounced off {}", scene.model_name(event.a), scene.model_name(event.b));
+    }
+}
 
-    // print!("eye {} center {} up {}", camera.eye, camera.center, camera.up);
-    // Model controls with rotation
-    if window.is_key_released(Key::J) ||
-    window.is_key_released(Key::L) ||
-    window.is_key_released(Key::I) ||
-    window.is_key_released(Key::K)
-    { // Move left
-        subject.set_rotation(Vec3::zeros());// Rotate counter-clockwise around Y-axis
+/// Keeps the orbit camera centered on the focused body while it moves.
+fn follow_focus(scene: &mut Scene, previous_position: Vec3, real_delta: f32) {
+    let position = scene.models[scene.camera.focus].get_position();
+    let moving = position != previous_position || scene.camera.is_retargeting();
+    if scene.camera.mode == CameraMode::Orbit && !scene.camera.is_bird_view && moving {
+        scene.camera.follow(position, real_delta);
     }
 }
 
+/// Casts a ray through the pixel under the cursor and focuses the orbit camera on the nearest body it hits.
+fn focus_at(scene: &mut Scene, x: f32, y: f32, width: f32, height: f32) {
+    let view = scene.camera.view();
+    let view_matrix = create_view_matrix(view.eye, view.center, view.up);
+    let perspective_matrix = create_perspective_matrix(width, height, scene.camera.field_of_view());
+
+    let Some(ray) = Ray::from_screen(x, y, width, height, &perspective_matrix, &view_matrix) else {
+        return;
+    };
+    let Some(index) = pick(&ray, &scene.models) else {
+        return;
+    };
+
+    if scene.camera.is_bird_view {
+        scene.camera.toogle_bird_view();
+    }
+    if scene.camera.mode != CameraMode::Orbit {
+        scene.camera.set_mode(CameraMode::Orbit);
+    }
+    scene.camera.focus_on(index, bounding_radius(scene.models[index].as_ref()));
+    println!("Following {}", scene.model_name(index));
+}
+
+/// Moves the chase camera after the ship and eases any camera mode switch along.
+fn chase_ship(scene: &mut Scene, real_delta: f32) {
+    if scene.camera.mode == CameraMode::Chase {
+        if let Some(ship) = scene.models[0].as_any().downcast_ref::<Ship>() {
+            let forward = ship.to_world(&Vec3::new(0.0, 0.0, -1.0));
+            let up = ship.to_world(&Vec3::y());
+            scene.camera.chase(ship.position, ship.velocity, forward, up, real_delta);
+        }
+    }
+    scene.camera.update(real_delta);
+}
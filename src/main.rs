@@ -1,6 +1,69 @@
-use cg_outer_wilds_planets::start;
+use std::path::PathBuf;
+
+use cg_outer_wilds_planets::{start, start_headless, RasterMode, RenderOptions};
+
+const DEFAULT_SCENE: &str = "./assets/scenes/solar_system.toml";
 
 fn main() {
-    println!("Hello, world!");
-    start();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let scene_path = arg_value::<PathBuf>(&args, "--scene")
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SCENE));
+
+    let camera_path = arg_value::<PathBuf>(&args, "--camera-path");
+
+    let mut options = RenderOptions::default();
+    if args.iter().any(|arg| arg == "--single-threaded") {
+        options.raster_mode = RasterMode::SingleThreaded;
+    }
+    if let Some(tone_mapping) = arg_value(&args, "--tone-mapping") {
+        options.tone_mapping = tone_mapping;
+    }
+    if let Some(exposure) = arg_value::<f32>(&args, "--exposure") {
+        if !exposure.is_finite() || exposure <= 0.0 {
+            eprintln!("Invalid value '{}' for --exposure: expected a finite number above 0", exposure);
+            std::process::exit(1);
+        }
+        options.exposure = exposure;
+    }
+    if args.iter().any(|arg| arg == "--no-post-processing") {
+        options.post_processing = false;
+    }
+
+    if args.iter().any(|arg| arg == "--headless") {
+        let frames = arg_value(&args, "--frames").unwrap_or(60);
+        let time_step = arg_value(&args, "--step").unwrap_or(0.1);
+        let output_dir = arg_value::<PathBuf>(&args, "--out").unwrap_or_else(|| PathBuf::from("./frames"));
+
+        if let Err(error) = start_headless(&scene_path, &options, frames, time_step, &output_dir, camera_path.as_deref()) {
+            eprintln!("Headless render failed: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(error) = start(&scene_path, &options, camera_path.as_deref()) {
+        eprintln!("Failed to start: {}", error);
+        std::process::exit(1);
+    }
+}
+
+/// Parses the value following `flag`, e.g. `--frames 120`. `None` when the flag is missing,
+/// a missing or malformed value exits with an error instead of falling back to the default.
+fn arg_value<T>(args: &[String], flag: &str) -> Option<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let index = args.iter().position(|arg| arg == flag)?;
+    let Some(value) = args.get(index + 1) else {
+        eprintln!("{} expects a value", flag);
+        std::process::exit(1);
+    };
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(error) => {
+            eprintln!("Invalid value '{}' for {}: {}", value, flag, error);
+            std::process::exit(1);
+        }
+    }
 }